@@ -18,6 +18,273 @@ export interface MetricsSummary {
     totalTokens: number;
     modelBreakdown: Array<ModelMetric>;
     avgLatencyMs: number;
+    latencySamples: number;
+    minLatencyMs: number;
+    maxLatencyMs: number;
+    latencyStdDevMs: number;
+    p50LatencyMs: number;
+    p90LatencyMs: number;
+    p95LatencyMs: number;
+    p99LatencyMs: number;
+    droppedSeries: number;
+}
+
+export declare enum MetricKind {
+    Counter = "counter",
+    Gauge = "gauge",
+    Histogram = "histogram",
+}
+
+export interface MetricDescriptor {
+    name: string;
+    kind: MetricKind;
+    help?: string;
+    buckets?: Array<number>;
+    maxSeries?: number;
+}
+
+export interface MetricSeries {
+    labels: Record<string, string>;
+    value: number;
+    count?: number;
+    bucketCounts?: Array<number>;
+}
+
+export interface MetricFamily {
+    name: string;
+    kind: MetricKind;
+    help: string;
+    buckets?: Array<number>;
+    series: Array<MetricSeries>;
+    droppedSeries: number;
+}
+
+export interface ModelPricing {
+    model: string;
+    provider?: string;
+    inputPerMillion: number;
+    outputPerMillion: number;
+    cachedPerMillion?: number;
+}
+
+export interface CostBudget {
+    dailyUsd?: number;
+    monthlyUsd?: number;
+}
+
+export interface UsageCost {
+    costUsd: number;
+    unpriced: boolean;
+    dailyBudgetCrossed: boolean;
+    monthlyBudgetCrossed: boolean;
+}
+
+export interface SpendEntry {
+    name: string;
+    usd: number;
+}
+
+export interface CostReport {
+    totalUsd: number;
+    dailyUsd: number;
+    monthlyUsd: number;
+    byModel: Array<SpendEntry>;
+    byProvider: Array<SpendEntry>;
+    unpricedTokens: number;
+    dailyBudgetExceeded: boolean;
+    monthlyBudgetExceeded: boolean;
+}
+
+export interface TraceContext {
+    traceId: string;
+    spanId?: string;
+}
+
+export declare enum HistoryResolution {
+    Minute = "minute",
+    Hour = "hour",
+    Day = "day",
+}
+
+export interface HistoryPoint {
+    startMs: number;
+    tokens: number;
+    costUsd: number;
+    latencyCount: number;
+    avgLatencyMs: number;
+    minLatencyMs: number;
+    maxLatencyMs: number;
+}
+
+export interface AudioFormat {
+    encoding: string;
+    channels: number;
+    sampleRate: number;
+}
+
+export interface VadOptions {
+    format?: AudioFormat;
+    mode?: string;
+    adaptive?: boolean;
+    startRatio?: number;
+    endRatio?: number;
+    captureUtterances?: boolean;
+    preRollMs?: number;
+    hangoverMs?: number;
+    maxUtteranceMs?: number;
+    minSpeechMs?: number;
+    minVoicedChunks?: number;
+    referenceFormat?: AudioFormat;
+    echoMaxDelayMs?: number;
+}
+
+export declare enum VadEventKind {
+    Silent = "silent",
+    SpeechStart = "speech_start",
+    Talking = "talking",
+    Silencing = "silencing",
+    SpeechEnd = "speech_end",
+    Panic = "panic",
+}
+
+export interface VadEvent {
+    kind: VadEventKind;
+    sampleOffset: number;
+    sampleCount: number;
+    rms: number;
+    segmentStartMs?: number;
+    segmentEndMs?: number;
+    peakRms?: number;
+    meanRms?: number;
+    utterance?: Buffer;
+    utteranceStartMs?: number;
+}
+
+export interface VadSegment {
+    startMs: number;
+    endMs: number;
+    peakRms: number;
+    meanRms: number;
+    utterance?: Buffer;
+    utteranceStartMs?: number;
+}
+
+export function segmentWav(input: string | Buffer, threshold: number, silenceTimeoutMs: number, options?: VadOptions | undefined | null): Promise<Array<VadSegment>>;
+
+export interface BackchannelOptions {
+    historyLimit?: number;
+    cooldownMs?: number;
+    minSpeechChunks?: number;
+    dropRatio?: number;
+    minActiveEnergy?: number;
+    format?: AudioFormat;
+    minPauseMs?: number;
+    maxPauseMs?: number;
+    pitchFallSemitones?: number;
+    requireFallingPitch?: boolean;
+}
+
+export declare enum BackchannelCueKind {
+    None = "none",
+    Continuer = "continuer",
+    Agreement = "agreement",
+    Surprise = "surprise",
+    Hold = "hold",
+}
+
+export interface BackchannelCue {
+    kind: BackchannelCueKind;
+    confidence: number;
+    pauseMs: number;
+    speechRate: number;
+    pitchFallSemitones: number;
+    pitchRiseSemitones: number;
+}
+
+export interface TurnTakingOptions {
+    format?: AudioFormat;
+    minActiveEnergy?: number;
+    maxSilenceMs?: number;
+    decisionThreshold?: number;
+}
+
+export interface TurnPrediction {
+    probability: number;
+    endOfTurn: boolean;
+    isSpeaking: boolean;
+    silenceMs: number;
+    energyDecay: number;
+    pitchDropSemitones: number;
+    hintScore: number;
+}
+
+export declare enum BargeInEvent {
+    Inactive = "inactive",
+    Idle = "idle",
+    Listening = "listening",
+    Interrupt = "interrupt",
+    Talking = "talking",
+    Backchannel = "backchannel",
+    Panic = "panic",
+}
+
+export interface BargeInOptions {
+    threshold?: number;
+    interruptMs?: number;
+    silenceTimeoutMs?: number;
+    vad?: VadOptions;
+}
+
+export interface DedupeOptions {
+    slidingExpiry?: boolean;
+}
+
+export interface BloomDedupeOptions {
+    expectedItems?: number;
+    falsePositiveRate?: number;
+    partitions?: number;
+}
+
+export interface BloomDedupeStats {
+    bitsPerPartition: number;
+    hashFunctions: number;
+    partitions: number;
+    fillRatio: number;
+    estimatedItems: number;
+    estimatedFalsePositiveRate: number;
+}
+
+export interface NearDedupeOptions {
+    maxDistance?: number;
+    maxSize?: number;
+    minWords?: number;
+}
+
+export interface NearDuplicateResult {
+    duplicate: boolean;
+    distance: number;
+    similarity: number;
+    fingerprint: string;
+    skipped: boolean;
+    evicted: boolean;
+}
+
+export declare enum RateLimitAlgorithm {
+    TokenBucket = "token_bucket",
+    SlidingWindow = "sliding_window",
+}
+
+export interface RateLimitOptions {
+    algorithm?: RateLimitAlgorithm;
+    limit: number;
+    windowMs: number;
+    maxKeys?: number;
+}
+
+export interface RateLimitDecision {
+    allowed: boolean;
+    remaining: number;
+    retryAfterMs: number;
 }
 
 export interface D2LAdapter {
@@ -29,26 +296,103 @@ export interface D2LAdapter {
 
 export class VadEngine {
     constructor(threshold: number, silenceTimeoutMs: number);
+    static withFormat(threshold: number, silenceTimeoutMs: number, format: AudioFormat): VadEngine;
+    static withOptions(threshold: number, silenceTimeoutMs: number, options: VadOptions): VadEngine;
     get lastRms(): number;
-    processChunk(chunk: Buffer | Uint8Array): string;
+    get lastResidualRms(): number;
+    get streamTimeMs(): number;
+    get noiseFloor(): number;
+    get startThreshold(): number;
+    get endThreshold(): number;
+    get lastEchoCorrelation(): number;
+    get echoDelayMs(): number;
+    get lastZeroCrossingRate(): number;
+    get lastSpectralFlatness(): number;
+    get lastVoiceBandRatio(): number;
+    processChunk(chunk: Buffer | Uint8Array): VadEventKind;
+    process(chunk: Buffer | Uint8Array): VadEvent;
+    flush(): VadEvent;
+    pushReference(chunk: Buffer | Uint8Array): void;
+    reset(): void;
 }
 
 export class BackchannelEngine {
     constructor(historyLimit: number, cooldownMs: number);
+    static withOptions(options: BackchannelOptions): BackchannelEngine;
+    setOptions(options: BackchannelOptions): void;
+    getOptions(): BackchannelOptions;
+    get energyMean(): number;
+    get energyStdDev(): number;
     processEnergy(rms: number): boolean;
+    processChunk(chunk: Buffer | Uint8Array): boolean;
+    classifyChunk(chunk: Buffer | Uint8Array): BackchannelCue;
+    get lastPitchHz(): number;
+    get pauseMs(): number;
+    reset(): void;
+}
+
+export class TurnTakingEngine {
+    constructor(options?: TurnTakingOptions | undefined | null);
+    processChunk(chunk: Buffer | Uint8Array, transcriptHint?: string | undefined | null): TurnPrediction;
+    reset(): void;
+}
+
+export class BargeInDetector {
+    constructor(options?: BargeInOptions | undefined | null);
+    setAssistantSpeaking(speaking: boolean): void;
+    pushReference(chunk: Buffer | Uint8Array): void;
+    get burstMs(): number;
+    processChunk(chunk: Buffer | Uint8Array): BargeInEvent;
 }
 
 export class MetricsEngine {
-    constructor();
-    recordTokens(model: string, count: number): void;
-    recordLatency(ms: number): void;
+    constructor(latencyWindow?: number | undefined | null);
+    static restore(snapshot: Buffer): MetricsEngine;
+    snapshot(): Buffer;
+    defineMetric(descriptor: MetricDescriptor): void;
+    incrementCounter(name: string, value?: number | undefined | null, labels?: Record<string, string> | undefined | null): boolean;
+    setGauge(name: string, value: number, labels?: Record<string, string> | undefined | null): boolean;
+    addGauge(name: string, delta: number, labels?: Record<string, string> | undefined | null): boolean;
+    observeHistogram(name: string, value: number, labels?: Record<string, string> | undefined | null): boolean;
+    recordTokens(model: string, count: number, labels?: Record<string, string> | undefined | null, timestampMs?: number | undefined | null): boolean;
+    recordLatency(ms: number, labels?: Record<string, string> | undefined | null, timestampMs?: number | undefined | null, trace?: TraceContext | undefined | null): boolean;
+    setPricing(pricing: Array<ModelPricing>): void;
+    setBudget(budget: CostBudget): void;
+    recordUsage(model: string, inputTokens: number, outputTokens: number, cachedTokens?: number | undefined | null, timestampMs?: number | undefined | null): UsageCost;
+    costReport(timestampMs?: number | undefined | null): CostReport;
+    history(resolution: HistoryResolution, fromMs: number, toMs: number): Array<HistoryPoint>;
+    exportOtlp(serviceName?: string | undefined | null, timestampMs?: number | undefined | null): Buffer;
+    collect(): Array<MetricFamily>;
+    renderPrometheus(openMetrics?: boolean | undefined | null): string;
     summarize(): MetricsSummary;
 }
 
 export class RatchetDedupe {
-    constructor(ttlMs: number, maxSize: number);
+    constructor(ttlMs: number, maxSize: number, options?: DedupeOptions | undefined | null);
+    check(key: string, timestampMs?: number | undefined | null, ttlMs?: number | undefined | null): boolean;
+    clear(): void;
+    size(): number;
+}
+
+export class RatchetBloomDedupe {
+    constructor(ttlMs: number, options?: BloomDedupeOptions | undefined | null);
     check(key: string, timestampMs?: number | undefined | null): boolean;
     clear(): void;
+    stats(): BloomDedupeStats;
+}
+
+export class RatchetNearDedupe {
+    constructor(ttlMs: number, options?: NearDedupeOptions | undefined | null);
+    check(text: string, timestampMs?: number | undefined | null, scope?: string | undefined | null): NearDuplicateResult;
+    clear(): void;
+    size(): number;
+}
+
+export class RatchetRateLimiter {
+    constructor(options: RateLimitOptions);
+    check(key: string, cost?: number | undefined | null, timestampMs?: number | undefined | null): RateLimitDecision;
+    reset(key: string): void;
+    clear(): void;
     size(): number;
 }
 
@@ -16,8 +16,10 @@
 //! - **Backchannel Heuristics**: Detection of "active listening" opportunities in voice streams.
 //! - **Doc-to-LoRA (D2L)**: Instant context internalization via dynamic LoRA adapters.
 
+use napi::bindgen_prelude::{AsyncTask, Buffer, Either};
 use napi_derive::napi;
 use indexmap::IndexMap;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH, Instant, Duration};
 use unicode_normalization::UnicodeNormalization;
@@ -91,304 +93,4967 @@ fn constant_time_eq(a: &str, b: &str) -> bool {
     result == 0
 }
 
+/// Wall-clock milliseconds since the Unix epoch, for callers that omit a timestamp.
+fn unix_time_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as f64
+}
+
+// --- PCM DECODING ---
+
+/// Internal analysis rate of the voice engines. Every input format is
+/// decoded, down-mixed and resampled to mono at this rate.
+const VAD_SAMPLE_RATE: u32 = 16_000;
+
+/// Converts a sample count at the analysis rate into milliseconds of audio.
+fn samples_to_ms(samples: u64) -> f64 {
+    samples as f64 * 1000.0 / VAD_SAMPLE_RATE as f64
+}
+
+/// Descriptor of the raw PCM layout delivered by a capture path.
+///
+/// [PT] Descritor do formato PCM bruto entregue pela captura de áudio.
+#[napi(object)]
+#[derive(Clone)]
+pub struct AudioFormat {
+    /// Sample encoding: `u8`, `s16le`, `s24le`, `s32le`, `f32le`, `mulaw` or `alaw`.
+    pub encoding: String,
+    /// Number of interleaved channels (down-mixed to mono).
+    pub channels: u32,
+    /// Input sample rate in Hz (resampled to 16 kHz).
+    pub sample_rate: u32,
+}
+
+impl AudioFormat {
+    /// Little-endian mono i16 at 16 kHz, the layout assumed by the legacy constructor.
+    fn default_pcm16() -> Self {
+        AudioFormat {
+            encoding: "s16le".to_string(),
+            channels: 1,
+            sample_rate: VAD_SAMPLE_RATE,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SampleEncoding {
+    U8,
+    S16Le,
+    S24Le,
+    S32Le,
+    F32Le,
+    MuLaw,
+    ALaw,
+}
+
+impl SampleEncoding {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "u8" | "pcm8" => Some(SampleEncoding::U8),
+            "s16le" | "pcm16" | "i16" => Some(SampleEncoding::S16Le),
+            "s24le" | "pcm24" | "i24" => Some(SampleEncoding::S24Le),
+            "s32le" | "pcm32" | "i32" => Some(SampleEncoding::S32Le),
+            "f32le" | "f32" | "float32" => Some(SampleEncoding::F32Le),
+            "mulaw" | "ulaw" | "pcmu" => Some(SampleEncoding::MuLaw),
+            "alaw" | "pcma" => Some(SampleEncoding::ALaw),
+            _ => None,
+        }
+    }
+
+    fn bytes_per_sample(self) -> usize {
+        match self {
+            SampleEncoding::S16Le => 2,
+            SampleEncoding::S24Le => 3,
+            SampleEncoding::S32Le | SampleEncoding::F32Le => 4,
+            SampleEncoding::U8 | SampleEncoding::MuLaw | SampleEncoding::ALaw => 1,
+        }
+    }
+
+    /// Decodes one sample, scaled to the i16 range so RMS thresholds stay
+    /// comparable across encodings.
+    fn decode(self, b: &[u8]) -> f32 {
+        match self {
+            SampleEncoding::U8 => (b[0] as f32 - 128.0) * 256.0,
+            SampleEncoding::S16Le => i16::from_le_bytes([b[0], b[1]]) as f32,
+            SampleEncoding::S24Le => (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 256.0,
+            SampleEncoding::S32Le => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 65_536.0,
+            SampleEncoding::F32Le => {
+                let v = f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+                if v.is_finite() { v.clamp(-1.0, 1.0) * 32_768.0 } else { 0.0 }
+            }
+            SampleEncoding::MuLaw => mulaw_to_linear(b[0]) as f32,
+            SampleEncoding::ALaw => alaw_to_linear(b[0]) as f32,
+        }
+    }
+}
+
+/// G.711 mu-law expansion.
+fn mulaw_to_linear(byte: u8) -> i16 {
+    let u = !byte;
+    let exponent = (u >> 4) & 0x07;
+    let mantissa = (u & 0x0F) as i16;
+    let magnitude = (((mantissa << 3) + 0x84) << exponent) - 0x84;
+    if u & 0x80 != 0 { -magnitude } else { magnitude }
+}
+
+/// G.711 A-law expansion.
+fn alaw_to_linear(byte: u8) -> i16 {
+    let a = byte ^ 0x55;
+    let exponent = (a >> 4) & 0x07;
+    let mantissa = (a & 0x0F) as i16;
+    let magnitude = match exponent {
+        0 => (mantissa << 4) + 8,
+        _ => ((mantissa << 4) + 0x108) << (exponent - 1),
+    };
+    if a & 0x80 != 0 { magnitude } else { -magnitude }
+}
+
+/// Longest anti-aliasing kernel; bounds the cost of very high input rates.
+const MAX_RESAMPLER_TAPS: usize = 511;
+
+/// Streaming linear-interpolation resampler with a windowed-sinc low-pass
+/// ahead of it when decimating. State carries across chunks so boundaries
+/// stay seamless.
+struct Resampler {
+    step: f64,
+    pos: f64,
+    history: Vec<f32>,
+    kernel: Vec<f32>,
+    filter_tail: Vec<f32>,
+}
+
+impl Resampler {
+    fn new(input_rate: u32, output_rate: u32) -> Self {
+        let step = input_rate as f64 / output_rate as f64;
+        let kernel = if step > 1.0 { low_pass_kernel(step) } else { Vec::new() };
+        Resampler {
+            step,
+            pos: 0.0,
+            history: Vec::new(),
+            filter_tail: vec![0.0; kernel.len().saturating_sub(1)],
+            kernel,
+        }
+    }
+
+    fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        if self.kernel.is_empty() {
+            self.history.extend_from_slice(input);
+        } else {
+            let taps = self.kernel.len();
+            let mut window: Vec<f32> = std::mem::take(&mut self.filter_tail);
+            window.extend_from_slice(input);
+            // The kernel is symmetric, so the convolution is a plain dot product.
+            for frame in window.windows(taps) {
+                self.history.push(frame.iter().zip(&self.kernel).map(|(x, k)| x * k).sum());
+            }
+            self.filter_tail = window[window.len() - (taps - 1)..].to_vec();
+        }
+
+        while self.pos + 1.0 < self.history.len() as f64 {
+            let i = self.pos.floor() as usize;
+            let frac = (self.pos - i as f64) as f32;
+            out.push(self.history[i] * (1.0 - frac) + self.history[i + 1] * frac);
+            self.pos += self.step;
+        }
+
+        let consumed = (self.pos.floor() as usize).min(self.history.len().saturating_sub(1));
+        self.history.drain(..consumed);
+        self.pos -= consumed as f64;
+    }
+
+    fn reset(&mut self) {
+        self.pos = 0.0;
+        self.history.clear();
+        self.filter_tail.iter_mut().for_each(|s| *s = 0.0);
+    }
+}
+
+/// Blackman-windowed sinc low-pass for decimating by `step`, normalized to
+/// unity gain at DC. The cutoff sits just under the output Nyquist frequency
+/// so the transition band ends near it and little folds back into the band.
+fn low_pass_kernel(step: f64) -> Vec<f32> {
+    let cutoff = 0.42 / step; // cycles per input sample
+    let taps = ((32.0 * step).ceil() as usize | 1).min(MAX_RESAMPLER_TAPS);
+    let mid = (taps - 1) as f64 / 2.0;
+    let span = (taps - 1) as f64;
+    let kernel: Vec<f64> = (0..taps)
+        .map(|n| {
+            let x = n as f64 - mid;
+            let sinc = if x == 0.0 {
+                2.0 * cutoff
+            } else {
+                (2.0 * std::f64::consts::PI * cutoff * x).sin() / (std::f64::consts::PI * x)
+            };
+            let phase = 2.0 * std::f64::consts::PI * n as f64 / span;
+            sinc * (0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos())
+        })
+        .collect();
+    let gain: f64 = kernel.iter().sum();
+    kernel.iter().map(|k| (k / gain) as f32).collect()
+}
+
+/// Turns raw interleaved PCM bytes into mono samples at the analysis rate.
+/// Partial frames are kept until the next chunk completes them.
+struct PcmDecoder {
+    encoding: SampleEncoding,
+    channels: usize,
+    pending: Vec<u8>,
+    resampler: Option<Resampler>,
+}
+
+impl PcmDecoder {
+    fn new(format: &AudioFormat, output_rate: u32) -> napi::Result<Self> {
+        let encoding = SampleEncoding::parse(&format.encoding).ok_or_else(|| {
+            napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Unsupported sample encoding: {}", format.encoding),
+            )
+        })?;
+        if format.channels == 0 || format.channels > 32 {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Invalid channel count: {}", format.channels),
+            ));
+        }
+        if !(1_000..=768_000).contains(&format.sample_rate) {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Invalid sample rate: {}", format.sample_rate),
+            ));
+        }
+        Ok(PcmDecoder {
+            encoding,
+            channels: format.channels as usize,
+            pending: Vec::new(),
+            resampler: (format.sample_rate != output_rate)
+                .then(|| Resampler::new(format.sample_rate, output_rate)),
+        })
+    }
+
+    fn decode(&mut self, chunk: &[u8]) -> Vec<f32> {
+        let frame_bytes = self.encoding.bytes_per_sample() * self.channels;
+        let mut bytes = std::mem::take(&mut self.pending);
+        bytes.extend_from_slice(chunk);
+
+        let complete = bytes.len() - bytes.len() % frame_bytes;
+        let width = self.encoding.bytes_per_sample();
+        let mono: Vec<f32> = bytes[..complete]
+            .chunks_exact(frame_bytes)
+            .map(|frame| {
+                let sum: f32 = frame.chunks_exact(width).map(|s| self.encoding.decode(s)).sum();
+                sum / self.channels as f32
+            })
+            .collect();
+        self.pending = bytes[complete..].to_vec();
+
+        match self.resampler.as_mut() {
+            Some(resampler) => {
+                let mut out = Vec::with_capacity(mono.len());
+                resampler.process(&mono, &mut out);
+                out
+            }
+            None => mono,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.pending.clear();
+        if let Some(resampler) = self.resampler.as_mut() {
+            resampler.reset();
+        }
+    }
+}
+
+// --- END PCM DECODING ---
+
+// --- SPECTRAL ANALYSIS ---
+
+/// Analysis frame length for spectral features (32 ms at 16 kHz).
+const SPECTRAL_FRAME: usize = 512;
+/// Lower and upper edges of the voice band, in Hz. The lower edge sits below
+/// the lowest speaking pitch: a low voice keeps much of its energy in the
+/// fundamental, which a 300 Hz telephone band would count as noise.
+const VOICE_BAND_HZ: (f32, f32) = (80.0, 3400.0);
+/// Minimum share of spectral energy inside the voice band for a voiced frame.
+/// An 85 Hz vowel scores about 0.75; brown noise and mains hum stay below 0.35.
+const MIN_VOICE_BAND_RATIO: f64 = 0.5;
+/// Maximum spectral flatness for a voiced frame (1.0 is white noise). Vowels
+/// at 10 dB SNR stay below 0.1; white and mildly colored noise stay above 0.23.
+const MAX_SPECTRAL_FLATNESS: f64 = 0.2;
+/// Maximum zero-crossing rate (crossings per sample) for a voiced frame.
+const MAX_ZERO_CROSSING_RATE: f64 = 0.4;
+
+/// In-place iterative radix-2 FFT. `re` and `im` must share a power-of-two length.
+fn fft_in_place(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two() && im.len() == n);
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f32::consts::PI / len as f32;
+        let (w_im, w_re) = angle.sin_cos();
+        for start in (0..n).step_by(len) {
+            let (mut cur_re, mut cur_im) = (1.0f32, 0.0f32);
+            for k in 0..len / 2 {
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * cur_re - im[b] * cur_im;
+                let t_im = re[b] * cur_im + im[b] * cur_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+                let next_re = cur_re * w_re - cur_im * w_im;
+                cur_im = cur_re * w_im + cur_im * w_re;
+                cur_re = next_re;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Per-chunk spectral descriptors used by the spectral detection mode.
+#[derive(Clone, Copy, Default)]
+struct SpectralFeatures {
+    zero_crossing_rate: f64,
+    flatness: f64,
+    voice_band_ratio: f64,
+}
+
+impl SpectralFeatures {
+    fn is_voice_like(&self) -> bool {
+        self.voice_band_ratio >= MIN_VOICE_BAND_RATIO
+            && self.flatness <= MAX_SPECTRAL_FLATNESS
+            && self.zero_crossing_rate <= MAX_ZERO_CROSSING_RATE
+    }
+}
+
+/// Hann-windowed FFT analyzer keeping the most recent frame of audio so
+/// chunks shorter than a frame still produce stable features.
+struct SpectralAnalyzer {
+    window: Vec<f32>,
+    tail: Vec<f32>,
+    band_bins: (usize, usize),
+}
+
+impl SpectralAnalyzer {
+    fn new(sample_rate: u32) -> Self {
+        let window = (0..SPECTRAL_FRAME)
+            .map(|i| {
+                let phase = 2.0 * std::f32::consts::PI * i as f32 / SPECTRAL_FRAME as f32;
+                0.5 - 0.5 * phase.cos()
+            })
+            .collect();
+        let bin_hz = sample_rate as f32 / SPECTRAL_FRAME as f32;
+        let band_bins = (
+            (VOICE_BAND_HZ.0 / bin_hz).ceil() as usize,
+            ((VOICE_BAND_HZ.1 / bin_hz).floor() as usize).min(SPECTRAL_FRAME / 2),
+        );
+        SpectralAnalyzer {
+            window,
+            tail: Vec::with_capacity(SPECTRAL_FRAME),
+            band_bins,
+        }
+    }
+
+    fn analyze(&mut self, samples: &[f32]) -> SpectralFeatures {
+        let crossings = samples
+            .windows(2)
+            .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+            .count();
+        let zero_crossing_rate = crossings as f64 / samples.len().max(1) as f64;
+
+        // Frames end at the newest sample and hop back by half a frame.
+        let mut audio = std::mem::take(&mut self.tail);
+        audio.extend_from_slice(samples);
+        let hop = SPECTRAL_FRAME / 2;
+        let mut frames = 0;
+        let (mut flatness, mut band_ratio) = (0.0, 0.0);
+        let new_start = audio.len() - samples.len();
+        let mut end = audio.len();
+        loop {
+            let start = end.saturating_sub(SPECTRAL_FRAME);
+            let (f, b) = self.frame_features(&audio[start..end]);
+            flatness += f;
+            band_ratio += b;
+            frames += 1;
+            if end <= new_start + hop || end < SPECTRAL_FRAME + hop {
+                break;
+            }
+            end -= hop;
+        }
+        let keep = audio.len().min(SPECTRAL_FRAME);
+        self.tail = audio[audio.len() - keep..].to_vec();
+
+        SpectralFeatures {
+            zero_crossing_rate,
+            flatness: flatness / frames as f64,
+            voice_band_ratio: band_ratio / frames as f64,
+        }
+    }
+
+    /// Returns (spectral flatness, voice-band energy ratio) for one frame,
+    /// zero-padding at the front when less than a full frame is available.
+    fn frame_features(&self, frame: &[f32]) -> (f64, f64) {
+        let pad = SPECTRAL_FRAME - frame.len();
+        let mut re = vec![0.0f32; SPECTRAL_FRAME];
+        let mut im = vec![0.0f32; SPECTRAL_FRAME];
+        for (i, &s) in frame.iter().enumerate() {
+            re[pad + i] = s * self.window[pad + i];
+        }
+        fft_in_place(&mut re, &mut im);
+
+        let mut total = 0.0f64;
+        let mut band = 0.0f64;
+        let mut log_sum = 0.0f64;
+        let bins = SPECTRAL_FRAME / 2;
+        for k in 1..=bins {
+            let power = (re[k] as f64).powi(2) + (im[k] as f64).powi(2) + 1e-10;
+            total += power;
+            log_sum += power.ln();
+            if (self.band_bins.0..=self.band_bins.1).contains(&k) {
+                band += power;
+            }
+        }
+        let arithmetic = total / bins as f64;
+        let geometric = (log_sum / bins as f64).exp();
+        (geometric / arithmetic, band / total)
+    }
+
+    fn reset(&mut self) {
+        self.tail.clear();
+    }
+}
+
+// --- END SPECTRAL ANALYSIS ---
+
+// --- ECHO SUPPRESSION ---
+
+/// Minimum normalized correlation between mic and far-end audio before the
+/// correlated component is treated as echo.
+const ECHO_MIN_CORRELATION: f64 = 0.3;
+/// Far-end audio retained beyond the delay search range.
+const ECHO_HISTORY_SLACK_MS: u32 = 1_000;
+/// Far-end RMS below which the reference is treated as silent playback.
+const ECHO_SILENT_REFERENCE_RMS: f64 = 1.0;
+
+/// Single-tap echo discounting against a far-end (playback) reference.
+///
+/// For every mic chunk the reference history is cross-correlated (via FFT)
+/// over the configured delay range; at the best-aligned delay the
+/// least-squares echo estimate is subtracted, leaving the residual that
+/// cannot be explained by playback. The transforms are skipped while the
+/// reference is silent or older than the delay search can reach.
+struct EchoSuppressor {
+    decoder: PcmDecoder,
+    history: Vec<f32>,
+    max_delay: usize,
+    capacity: usize,
+    /// Mic samples analyzed since the last reference push.
+    since_push: usize,
+    last_correlation: f64,
+    last_delay: usize,
+}
+
+impl EchoSuppressor {
+    fn new(format: &AudioFormat, max_delay_ms: u32) -> napi::Result<Self> {
+        let max_delay = ms_to_samples(max_delay_ms);
+        Ok(EchoSuppressor {
+            decoder: PcmDecoder::new(format, VAD_SAMPLE_RATE)?,
+            history: Vec::new(),
+            max_delay,
+            capacity: max_delay + ms_to_samples(ECHO_HISTORY_SLACK_MS),
+            since_push: 0,
+            last_correlation: 0.0,
+            last_delay: 0,
+        })
+    }
+
+    fn push(&mut self, chunk: &[u8]) {
+        let samples = self.decoder.decode(chunk);
+        self.history.extend_from_slice(&samples);
+        self.since_push = 0;
+        if self.history.len() > self.capacity {
+            let excess = self.history.len() - self.capacity;
+            self.history.drain(..excess);
+        }
+    }
+
+    /// Returns the echo-free residual of `mic`, or `None` when no playback
+    /// correlates with it.
+    fn suppress(&mut self, mic: &[f32]) -> Option<Vec<f32>> {
+        self.last_correlation = 0.0;
+        let len = mic.len();
+        let idle = self.since_push;
+        self.since_push += len;
+        if idle > self.max_delay {
+            // Playback stopped: every far-end sample is beyond the delay search.
+            self.history.clear();
+            return None;
+        }
+        let span = self.history.len().min(len + self.max_delay);
+        if len == 0 || span < len {
+            return None;
+        }
+        let reference = &self.history[self.history.len() - span..];
+
+        let mut prefix = Vec::with_capacity(span + 1);
+        prefix.push(0.0f64);
+        for &s in reference {
+            prefix.push(prefix[prefix.len() - 1] + (s as f64) * (s as f64));
+        }
+        let mic_energy: f64 = mic.iter().map(|&s| (s as f64) * (s as f64)).sum();
+        if mic_energy <= 0.0 || prefix[span] < span as f64 * ECHO_SILENT_REFERENCE_RMS.powi(2) {
+            return None;
+        }
+
+        // Cross-correlation: corr[k] = sum_i reference[k + i] * mic[i].
+        let n = (span + len).next_power_of_two();
+        let (mut ref_re, mut ref_im) = (vec![0.0f32; n], vec![0.0f32; n]);
+        let (mut mic_re, mut mic_im) = (vec![0.0f32; n], vec![0.0f32; n]);
+        ref_re[..span].copy_from_slice(reference);
+        mic_re[..len].copy_from_slice(mic);
+        fft_in_place(&mut ref_re, &mut ref_im);
+        fft_in_place(&mut mic_re, &mut mic_im);
+        // Inverse transform of A * conj(B) computed as conj(FFT(conj(A * conj(B)))) / n.
+        for i in 0..n {
+            let re = ref_re[i] * mic_re[i] + ref_im[i] * mic_im[i];
+            let im = ref_im[i] * mic_re[i] - ref_re[i] * mic_im[i];
+            ref_re[i] = re;
+            ref_im[i] = -im;
+        }
+        fft_in_place(&mut ref_re, &mut ref_im);
+
+        let mut best: Option<(usize, f64, f64)> = None;
+        for k in 0..=(span - len) {
+            let ref_energy = prefix[k + len] - prefix[k];
+            if ref_energy <= 1e-6 {
+                continue;
+            }
+            let cross = ref_re[k] as f64 / n as f64;
+            let correlation = cross / (mic_energy * ref_energy).sqrt();
+            if best.is_none_or(|(_, c, _)| correlation > c) {
+                best = Some((k, correlation, cross / ref_energy));
+            }
+        }
+
+        let (k, correlation, gain) = best?;
+        self.last_correlation = correlation.max(0.0);
+        self.last_delay = span - len - k;
+        if correlation < ECHO_MIN_CORRELATION {
+            return None;
+        }
+        let aligned = &reference[k..k + len];
+        Some(mic.iter().zip(aligned).map(|(&m, &r)| m - gain as f32 * r).collect())
+    }
+
+    fn reset(&mut self) {
+        self.decoder.reset();
+        self.history.clear();
+        self.since_push = 0;
+        self.last_correlation = 0.0;
+        self.last_delay = 0;
+    }
+}
+
+// --- END ECHO SUPPRESSION ---
+
+/// Lowest noise floor the adaptive tracker will report (about -60 dBFS),
+/// so digital silence cannot collapse the thresholds to zero.
+const NOISE_FLOOR_MIN: f64 = 30.0;
+/// Span of the minimum-statistics window. Natural speech pauses within this
+/// span, so its minimum follows the background rather than the voice.
+const NOISE_FLOOR_WINDOW_MS: f64 = 3_000.0;
+/// Number of sub-blocks the window is split into.
+const NOISE_FLOOR_BLOCKS: usize = 12;
+/// Smoothing time constant applied to the windowed minimum.
+const NOISE_FLOOR_SMOOTH_MS: f64 = 200.0;
+/// Initial audio used only to learn the floor before any decision is made.
+const NOISE_FLOOR_CALIBRATION_MS: f64 = 200.0;
+
+/// Minimum-statistics tracker of the ambient RMS level.
+struct NoiseFloorTracker {
+    floor: f64,
+    elapsed_ms: f64,
+    block_min: f64,
+    block_elapsed_ms: f64,
+    block_mins: std::collections::VecDeque<f64>,
+}
+
+impl NoiseFloorTracker {
+    fn new(initial_floor: f64) -> Self {
+        NoiseFloorTracker {
+            floor: initial_floor.max(NOISE_FLOOR_MIN),
+            elapsed_ms: 0.0,
+            block_min: f64::INFINITY,
+            block_elapsed_ms: 0.0,
+            block_mins: std::collections::VecDeque::with_capacity(NOISE_FLOOR_BLOCKS + 1),
+        }
+    }
+
+    fn is_calibrating(&self) -> bool {
+        self.elapsed_ms < NOISE_FLOOR_CALIBRATION_MS
+    }
+
+    fn update(&mut self, rms: f64, duration_ms: f64) {
+        let calibrating = self.is_calibrating();
+        self.elapsed_ms += duration_ms;
+        self.block_min = self.block_min.min(rms);
+        self.block_elapsed_ms += duration_ms;
+        if self.block_elapsed_ms >= NOISE_FLOOR_WINDOW_MS / NOISE_FLOOR_BLOCKS as f64 {
+            self.block_mins.push_back(self.block_min);
+            if self.block_mins.len() > NOISE_FLOOR_BLOCKS {
+                self.block_mins.pop_front();
+            }
+            self.block_min = f64::INFINITY;
+            self.block_elapsed_ms = 0.0;
+        }
+
+        let target = self.block_mins.iter().copied().fold(self.block_min, f64::min);
+        if calibrating {
+            self.floor = target.max(NOISE_FLOOR_MIN);
+        } else {
+            let alpha = 1.0 - (-duration_ms / NOISE_FLOOR_SMOOTH_MS).exp();
+            self.floor = (self.floor + (target - self.floor) * alpha).max(NOISE_FLOOR_MIN);
+        }
+    }
+}
+
+/// Speech decision strategy of a [`VadEngine`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum DetectionMode {
+    /// RMS energy above threshold.
+    Energy,
+    /// Energy gate combined with zero-crossing rate, spectral flatness and voice-band energy.
+    Spectral,
+}
+
+impl DetectionMode {
+    fn parse(name: &str) -> napi::Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "energy" | "rms" => Ok(DetectionMode::Energy),
+            "spectral" => Ok(DetectionMode::Spectral),
+            _ => Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Unknown VAD mode: {}", name),
+            )),
+        }
+    }
+}
+
+/// Optional construction parameters for [`VadEngine::with_options`].
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct VadOptions {
+    /// Capture format. Defaults to little-endian mono i16 at 16 kHz.
+    pub format: Option<AudioFormat>,
+    /// Detection mode: `energy` (default) or `spectral`.
+    pub mode: Option<String>,
+    /// Derives thresholds from a continuously estimated noise floor instead of
+    /// the fixed `threshold`, which then only seeds the initial estimate.
+    pub adaptive: Option<bool>,
+    /// Start threshold as a multiple of the noise floor. Default: 3.0.
+    pub start_ratio: Option<f64>,
+    /// End threshold as a multiple of the noise floor; lower than `start_ratio`
+    /// for hysteresis. Default: 2.0.
+    pub end_ratio: Option<f64>,
+    /// Keeps audio natively and attaches the full utterance to `speech_end` events.
+    pub capture_utterances: Option<bool>,
+    /// Audio kept from before `speech_start`. Default: 300 ms.
+    pub pre_roll_ms: Option<u32>,
+    /// Trailing silence kept after the speech, at most the silence timeout. Default: 200 ms.
+    pub hangover_ms: Option<u32>,
+    /// Upper bound on a captured utterance; longer speech is truncated. Default: 30000 ms.
+    pub max_utterance_ms: Option<u32>,
+    /// Minimum voiced audio before `speech_start` is confirmed. Default: 0 ms.
+    pub min_speech_ms: Option<u32>,
+    /// Minimum consecutive voiced chunks before `speech_start` is confirmed. Default: 1.
+    pub min_voiced_chunks: Option<u32>,
+    /// Format of the far-end audio given to `pushReference`. Defaults to the capture format.
+    pub reference_format: Option<AudioFormat>,
+    /// Largest playback-to-mic delay searched for echo. Default: 300 ms.
+    pub echo_max_delay_ms: Option<u32>,
+}
+
+/// State reported for each processed chunk.
+#[napi(string_enum = "snake_case")]
+#[derive(PartialEq, Debug)]
+pub enum VadEventKind {
+    Silent,
+    SpeechStart,
+    Talking,
+    Silencing,
+    SpeechEnd,
+    Panic,
+}
+
+/// Structured result of [`VadEngine::process`].
+///
+/// Sample offsets count mono samples at the 16 kHz analysis rate; times are
+/// milliseconds of stream audio, independent of the input format.
+#[napi(object)]
+pub struct VadEvent {
+    pub kind: VadEventKind,
+    /// Stream offset of the first sample of this chunk.
+    pub sample_offset: f64,
+    /// Number of analysis samples decoded from this chunk.
+    pub sample_count: u32,
+    /// RMS energy of this chunk, after far-end echo was discounted.
+    pub rms: f64,
+    /// Start of the current segment; set while a segment is open and on `speech_end`.
+    pub segment_start_ms: Option<f64>,
+    /// End of the speech in the segment (start of the trailing silence); set on `speech_end`.
+    pub segment_end_ms: Option<f64>,
+    /// Highest chunk RMS among the voiced chunks of the segment.
+    pub peak_rms: Option<f64>,
+    /// Duration-weighted mean RMS of the voiced chunks of the segment.
+    pub mean_rms: Option<f64>,
+    /// Pre-roll + speech + hangover as little-endian mono i16 PCM at 16 kHz.
+    /// Only on `speech_end` when utterance capture is enabled.
+    pub utterance: Option<Buffer>,
+    /// Stream time of the first sample of `utterance`.
+    pub utterance_start_ms: Option<f64>,
+}
+
+/// Buffer-free form of [`VadEvent`], used by the native callers of the
+/// state machine (barge-in, offline segmentation) and converted at the edge.
+pub struct VadStep {
+    kind: VadEventKind,
+    sample_offset: u64,
+    sample_count: usize,
+    rms: f64,
+    segment_start_ms: Option<f64>,
+    segment_end_ms: Option<f64>,
+    peak_rms: Option<f64>,
+    mean_rms: Option<f64>,
+    /// Start offset and s16le PCM of the captured utterance.
+    utterance: Option<(u64, Vec<u8>)>,
+}
+
+impl VadStep {
+    fn in_segment(&self) -> bool {
+        matches!(self.kind, VadEventKind::SpeechStart | VadEventKind::Talking | VadEventKind::Silencing)
+    }
+}
+
+impl From<VadStep> for VadEvent {
+    fn from(step: VadStep) -> Self {
+        let (utterance_start_ms, utterance) = match step.utterance {
+            Some((start, pcm)) => (Some(samples_to_ms(start)), Some(pcm.into())),
+            None => (None, None),
+        };
+        VadEvent {
+            kind: step.kind,
+            sample_offset: step.sample_offset as f64,
+            sample_count: step.sample_count as u32,
+            rms: step.rms,
+            segment_start_ms: step.segment_start_ms,
+            segment_end_ms: step.segment_end_ms,
+            peak_rms: step.peak_rms,
+            mean_rms: step.mean_rms,
+            utterance,
+            utterance_start_ms,
+        }
+    }
+}
+
+/// RMS level of a block of samples (`0` when empty).
+fn chunk_rms(samples: &[f32]) -> f64 {
+    let sum_sq: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    (sum_sq / samples.len().max(1) as f64).sqrt()
+}
+
+/// Converts milliseconds of audio into a sample count at the analysis rate.
+fn ms_to_samples(ms: u32) -> usize {
+    (ms as u64 * VAD_SAMPLE_RATE as u64 / 1000) as usize
+}
+
+/// Native audio retention for utterance capture: a ring of pre-roll audio
+/// while idle and a bounded buffer of the active segment.
+struct UtteranceCapture {
+    pre_roll: std::collections::VecDeque<f32>,
+    pre_roll_capacity: usize,
+    hangover: usize,
+    max_samples: usize,
+    start: u64,
+    samples: Vec<f32>,
+}
+
+impl UtteranceCapture {
+    fn new(pre_roll_ms: u32, hangover_ms: u32, max_utterance_ms: u32) -> Self {
+        let pre_roll_capacity = ms_to_samples(pre_roll_ms);
+        UtteranceCapture {
+            pre_roll: std::collections::VecDeque::with_capacity(pre_roll_capacity),
+            pre_roll_capacity,
+            hangover: ms_to_samples(hangover_ms),
+            max_samples: ms_to_samples(max_utterance_ms),
+            start: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    fn feed_idle(&mut self, samples: &[f32]) {
+        let skip = samples.len().saturating_sub(self.pre_roll_capacity);
+        for &sample in &samples[skip..] {
+            if self.pre_roll.len() == self.pre_roll_capacity {
+                self.pre_roll.pop_front();
+            }
+            self.pre_roll.push_back(sample);
+        }
+    }
+
+    /// Opens an utterance whose speech begins at stream position `speech_start`.
+    fn begin(&mut self, speech_start: u64) {
+        self.start = speech_start - self.pre_roll.len() as u64;
+        self.samples.clear();
+        self.samples.extend(self.pre_roll.drain(..));
+    }
+
+    fn feed_active(&mut self, samples: &[f32]) {
+        let room = self.max_samples.saturating_sub(self.samples.len());
+        self.samples.extend_from_slice(&samples[..samples.len().min(room)]);
+    }
+
+    /// Closes the utterance at `speech_end` plus hangover and encodes it as s16le.
+    fn finish(&mut self, speech_end: u64) -> (u64, Vec<u8>) {
+        let end = ((speech_end - self.start) as usize + self.hangover).min(self.samples.len());
+        let pcm = self.samples[..end]
+            .iter()
+            .flat_map(|&s| (s.clamp(-32_768.0, 32_767.0) as i16).to_le_bytes())
+            .collect();
+        self.samples.clear();
+        (self.start, pcm)
+    }
+
+    /// Drops an unconfirmed utterance, recycling its audio as pre-roll.
+    fn abandon(&mut self) {
+        let samples = std::mem::take(&mut self.samples);
+        self.feed_idle(&samples);
+    }
+
+    fn reset(&mut self) {
+        self.pre_roll.clear();
+        self.samples.clear();
+    }
+}
+
+/// Running statistics of the current (or last finished) speech segment.
+#[derive(Clone, Copy, Default)]
+struct SegmentStats {
+    start: u64,
+    end: Option<u64>,
+    peak_rms: f64,
+    weighted_rms: f64,
+    voiced_samples: u64,
+}
+
+impl SegmentStats {
+    fn add(&mut self, rms: f64, samples: u64) {
+        self.peak_rms = self.peak_rms.max(rms);
+        self.weighted_rms += rms * samples as f64;
+        self.voiced_samples += samples;
+    }
+
+    fn mean_rms(&self) -> f64 {
+        if self.voiced_samples == 0 { 0.0 } else { self.weighted_rms / self.voiced_samples as f64 }
+    }
+}
+
 /// Real-time Voice Activity Detection (VAD) Engine.
 /// 
 /// [PT] Motor de Detecção de Atividade de Voz (VAD) em tempo real.
 /// 
 /// Implements a robust RMS-based energy detector with configurable threshold 
-/// governance and temporal silence suppression logic.
+/// governance and temporal silence suppression logic. An optional spectral
+/// mode additionally requires speech-like spectra, rejecting clicks and hum.
+#[napi]
+pub struct VadEngine {
+    is_talking: bool,
+    // Timing runs on the sample clock, not the wall clock, so segmentation
+    // only depends on the audio itself and offline replays are reproducible.
+    stream_position: u64,
+    silence_start: Option<u64>,
+    segment: SegmentStats,
+    voiced_run: u32,
+    min_speech_ms: u32,
+    min_voiced_chunks: u32,
+    threshold: f64,
+    silence_timeout_ms: u64,
+    last_rms: f64,
+    last_residual_rms: f64,
+    decoder: PcmDecoder,
+    mode: DetectionMode,
+    spectral: SpectralAnalyzer,
+    last_features: SpectralFeatures,
+    noise_floor: Option<NoiseFloorTracker>,
+    start_ratio: f64,
+    end_ratio: f64,
+    capture: Option<UtteranceCapture>,
+    echo: EchoSuppressor,
+}
+
+#[napi]
+impl VadEngine {
+    /// Initializes a new VAD processor with specific acoustic sensitivity.
+    /// Chunks are expected as little-endian mono i16 PCM at 16 kHz.
+    #[napi(constructor)]
+    pub fn new(threshold: f64, silence_timeout_ms: u32) -> Self {
+        Self::with_options(threshold, silence_timeout_ms, VadOptions::default())
+            .expect("default VAD options are always valid")
+    }
+
+    /// Initializes a VAD processor for an arbitrary capture format.
+    /// Decoding, down-mixing and resampling happen natively before analysis.
+    #[napi(factory)]
+    pub fn with_format(threshold: f64, silence_timeout_ms: u32, format: AudioFormat) -> napi::Result<Self> {
+        Self::with_options(threshold, silence_timeout_ms, VadOptions { format: Some(format), ..Default::default() })
+    }
+
+    /// Initializes a VAD processor with the full set of optional parameters.
+    #[napi(factory)]
+    pub fn with_options(threshold: f64, silence_timeout_ms: u32, options: VadOptions) -> napi::Result<Self> {
+        let format = options.format.unwrap_or_else(AudioFormat::default_pcm16);
+        let reference_format = options.reference_format.unwrap_or_else(|| format.clone());
+        let mode = match options.mode.as_deref() {
+            Some(name) => DetectionMode::parse(name)?,
+            None => DetectionMode::Energy,
+        };
+        let start_ratio = options.start_ratio.unwrap_or(3.0);
+        let end_ratio = options.end_ratio.unwrap_or(2.0);
+        if !(start_ratio >= 1.0 && end_ratio >= 1.0 && end_ratio <= start_ratio) {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Invalid hysteresis ratios: start {} / end {}", start_ratio, end_ratio),
+            ));
+        }
+        let noise_floor = options
+            .adaptive
+            .unwrap_or(false)
+            .then(|| NoiseFloorTracker::new(threshold / start_ratio));
+        let capture = options.capture_utterances.unwrap_or(false).then(|| {
+            UtteranceCapture::new(
+                options.pre_roll_ms.unwrap_or(300),
+                options.hangover_ms.unwrap_or(200),
+                options.max_utterance_ms.unwrap_or(30_000),
+            )
+        });
+        Ok(VadEngine {
+            is_talking: false,
+            stream_position: 0,
+            silence_start: None,
+            segment: SegmentStats::default(),
+            voiced_run: 0,
+            min_speech_ms: options.min_speech_ms.unwrap_or(0),
+            min_voiced_chunks: options.min_voiced_chunks.unwrap_or(1).max(1),
+            threshold,
+            silence_timeout_ms: silence_timeout_ms as u64,
+            last_rms: 0.0,
+            last_residual_rms: 0.0,
+            decoder: PcmDecoder::new(&format, VAD_SAMPLE_RATE)?,
+            mode,
+            spectral: SpectralAnalyzer::new(VAD_SAMPLE_RATE),
+            last_features: SpectralFeatures::default(),
+            noise_floor,
+            start_ratio,
+            end_ratio,
+            capture,
+            echo: EchoSuppressor::new(&reference_format, options.echo_max_delay_ms.unwrap_or(300))?,
+        })
+    }
+
+    /// Returns the RMS (Root Mean Square) energy of the last processed chunk.
+    #[napi(getter)]
+    pub fn last_rms(&self) -> f64 {
+        self.last_rms
+    }
+
+    /// RMS of the last chunk after far-end echo was discounted; the level the
+    /// speech decision used. Equals `lastRms` when no echo was removed.
+    #[napi(getter)]
+    pub fn last_residual_rms(&self) -> f64 {
+        self.last_residual_rms
+    }
+
+    /// Audio time consumed since construction or the last reset, in milliseconds.
+    #[napi(getter)]
+    pub fn stream_time_ms(&self) -> f64 {
+        samples_to_ms(self.stream_position)
+    }
+
+    /// Current ambient noise floor estimate (RMS). Equals `0` when adaptive mode is off.
+    #[napi(getter)]
+    pub fn noise_floor(&self) -> f64 {
+        self.noise_floor.as_ref().map_or(0.0, |tracker| tracker.floor)
+    }
+
+    /// RMS level a chunk must exceed to start a speech segment.
+    #[napi(getter)]
+    pub fn start_threshold(&self) -> f64 {
+        match &self.noise_floor {
+            Some(tracker) => tracker.floor * self.start_ratio,
+            None => self.threshold,
+        }
+    }
+
+    /// RMS level a chunk must exceed to keep an active speech segment alive.
+    #[napi(getter)]
+    pub fn end_threshold(&self) -> f64 {
+        match &self.noise_floor {
+            Some(tracker) => tracker.floor * self.end_ratio,
+            None => self.threshold,
+        }
+    }
+
+    /// Normalized correlation (0-1) between the last chunk and the far-end reference.
+    #[napi(getter)]
+    pub fn last_echo_correlation(&self) -> f64 {
+        self.echo.last_correlation
+    }
+
+    /// Playback-to-mic delay estimated for the last chunk, in milliseconds.
+    #[napi(getter)]
+    pub fn echo_delay_ms(&self) -> f64 {
+        samples_to_ms(self.echo.last_delay as u64)
+    }
+
+    /// Zero-crossing rate (crossings per sample) of the last chunk. Spectral mode only.
+    #[napi(getter)]
+    pub fn last_zero_crossing_rate(&self) -> f64 {
+        self.last_features.zero_crossing_rate
+    }
+
+    /// Spectral flatness (0 = tonal, 1 = white noise) of the last chunk. Spectral mode only.
+    #[napi(getter)]
+    pub fn last_spectral_flatness(&self) -> f64 {
+        self.last_features.flatness
+    }
+
+    /// Share of spectral energy in the 80-3400 Hz voice band for the last chunk. Spectral mode only.
+    #[napi(getter)]
+    pub fn last_voice_band_ratio(&self) -> f64 {
+        self.last_features.voice_band_ratio
+    }
+
+    /// Conducts acoustic analysis on a discrete segment of PCM audio.
+    #[napi]
+    pub fn process_chunk(&mut self, chunk: Buffer) -> VadEventKind {
+        self.step(&chunk).kind
+    }
+
+    /// Analyzes a chunk and returns a structured event with stream offsets
+    /// and segment boundaries, so callers can cut exact utterance audio.
+    #[napi]
+    pub fn process(&mut self, chunk: Buffer) -> VadEvent {
+        self.step(&chunk).into()
+    }
+
+    /// Closes the stream: an open segment ends where its trailing silence began
+    /// (or at the last sample) and an unconfirmed speech candidate is dropped.
+    /// Returns `speech_end` when a segment was closed, `silent` otherwise.
+    #[napi]
+    pub fn flush(&mut self) -> VadEvent {
+        self.finish().into()
+    }
+
+    fn step(&mut self, chunk: &[u8]) -> VadStep {
+        if is_panic_mode() {
+            return self.event(VadEventKind::Panic, self.stream_position, 0);
+        }
+
+        let samples = if chunk.is_empty() { Vec::new() } else { self.decoder.decode(chunk) };
+        if samples.is_empty() {
+            return self.event(VadEventKind::Silent, self.stream_position, 0);
+        }
+
+        // Decisions run on the echo residual; captured audio stays untouched.
+        let residual = self.echo.suppress(&samples);
+        let analysis = residual.as_deref().unwrap_or(&samples);
+        let rms = chunk_rms(analysis);
+        self.last_rms = if residual.is_some() { chunk_rms(&samples) } else { rms };
+        self.last_residual_rms = rms;
+
+        let threshold = if self.is_talking { self.end_threshold() } else { self.start_threshold() };
+        let mut voiced = match self.mode {
+            DetectionMode::Energy => rms > threshold,
+            DetectionMode::Spectral => {
+                // Features are always computed so the frame history stays continuous.
+                self.last_features = self.spectral.analyze(analysis);
+                rms > threshold && self.last_features.is_voice_like()
+            }
+        };
+
+        if let Some(tracker) = self.noise_floor.as_mut() {
+            // No decisions until the floor has been learned from real input.
+            voiced &= !tracker.is_calibrating();
+            tracker.update(rms, samples_to_ms(samples.len() as u64));
+        }
+
+        let chunk_start = self.stream_position;
+        self.stream_position += samples.len() as u64;
+
+        let kind = if voiced {
+            self.silence_start = None;
+            if self.is_talking {
+                self.segment.add(rms, samples.len() as u64);
+                if let Some(capture) = self.capture.as_mut() {
+                    capture.feed_active(&samples);
+                }
+                VadEventKind::Talking
+            } else {
+                // Speech is only confirmed once the voiced run is long enough;
+                // until then the candidate is reported as silence.
+                if self.voiced_run == 0 {
+                    self.segment = SegmentStats { start: chunk_start, ..Default::default() };
+                    if let Some(capture) = self.capture.as_mut() {
+                        capture.begin(chunk_start);
+                    }
+                }
+                self.voiced_run += 1;
+                self.segment.add(rms, samples.len() as u64);
+                if let Some(capture) = self.capture.as_mut() {
+                    capture.feed_active(&samples);
+                }
+                let run_ms = samples_to_ms(self.stream_position - self.segment.start);
+                if self.voiced_run >= self.min_voiced_chunks && run_ms >= self.min_speech_ms as f64 {
+                    self.is_talking = true;
+                    VadEventKind::SpeechStart
+                } else {
+                    VadEventKind::Silent
+                }
+            }
+        } else if self.is_talking {
+            let start = *self.silence_start.get_or_insert(chunk_start);
+            if let Some(capture) = self.capture.as_mut() {
+                capture.feed_active(&samples);
+            }
+            if samples_to_ms(self.stream_position - start) > self.silence_timeout_ms as f64 {
+                self.is_talking = false;
+                self.voiced_run = 0;
+                self.silence_start = None;
+                self.segment.end = Some(start);
+                VadEventKind::SpeechEnd
+            } else {
+                VadEventKind::Silencing
+            }
+        } else {
+            if let Some(capture) = self.capture.as_mut() {
+                if self.voiced_run > 0 {
+                    // A discarded false start becomes pre-roll for the next candidate.
+                    capture.abandon();
+                }
+                capture.feed_idle(&samples);
+            }
+            self.voiced_run = 0;
+            VadEventKind::Silent
+        };
+
+        let mut event = self.event(kind, chunk_start, samples.len());
+        if kind == VadEventKind::SpeechEnd {
+            self.attach_utterance(&mut event, &samples);
+        }
+        event
+    }
+
+    fn finish(&mut self) -> VadStep {
+        if !self.is_talking {
+            if self.voiced_run > 0 {
+                if let Some(capture) = self.capture.as_mut() {
+                    capture.abandon();
+                }
+                self.voiced_run = 0;
+            }
+            return self.event(VadEventKind::Silent, self.stream_position, 0);
+        }
+
+        self.is_talking = false;
+        self.voiced_run = 0;
+        self.segment.end = Some(self.silence_start.take().unwrap_or(self.stream_position));
+        let mut event = self.event(VadEventKind::SpeechEnd, self.stream_position, 0);
+        self.attach_utterance(&mut event, &[]);
+        event
+    }
+
+    /// Discards decoder state (partial frames, resampler history), rewinds the
+    /// sample clock and returns to idle.
+    #[napi]
+    pub fn reset(&mut self) {
+        self.is_talking = false;
+        self.stream_position = 0;
+        self.silence_start = None;
+        self.segment = SegmentStats::default();
+        self.voiced_run = 0;
+        self.last_rms = 0.0;
+        self.last_residual_rms = 0.0;
+        self.decoder.reset();
+        self.spectral.reset();
+        self.last_features = SpectralFeatures::default();
+        if let Some(capture) = self.capture.as_mut() {
+            capture.reset();
+        }
+        self.echo.reset();
+    }
+
+    /// Supplies far-end (playback) audio, e.g. the assistant's own TTS, so
+    /// energy correlated with it is discounted before the speech decision.
+    ///
+    /// Push each chunk as it is handed to the audio output, not when it is
+    /// synthesized: the delay search only spans `echoMaxDelayMs`, and only
+    /// about one second of reference beyond it is kept, so audio pushed ahead
+    /// of playback is gone or misaligned by the time its echo reaches the mic.
+    #[napi]
+    pub fn push_reference(&mut self, chunk: Buffer) {
+        self.feed_reference(&chunk);
+    }
+
+    fn feed_reference(&mut self, chunk: &[u8]) {
+        if is_panic_mode() {
+            return;
+        }
+        self.echo.push(chunk);
+    }
+
+    fn attach_utterance(&mut self, event: &mut VadStep, trailing: &[f32]) {
+        if let Some(capture) = self.capture.as_mut() {
+            event.utterance = Some(capture.finish(self.segment.end.unwrap_or(self.stream_position)));
+            // The rest of the ending chunk may already precede the next utterance.
+            capture.feed_idle(trailing);
+        }
+    }
+
+    fn event(&self, kind: VadEventKind, offset: u64, sample_count: usize) -> VadStep {
+        let in_segment = matches!(
+            kind,
+            VadEventKind::SpeechStart | VadEventKind::Talking | VadEventKind::Silencing | VadEventKind::SpeechEnd
+        );
+        VadStep {
+            kind,
+            sample_offset: offset,
+            sample_count,
+            rms: if sample_count > 0 { self.last_residual_rms } else { 0.0 },
+            segment_start_ms: in_segment.then(|| samples_to_ms(self.segment.start)),
+            segment_end_ms: if in_segment { self.segment.end.map(samples_to_ms) } else { None },
+            peak_rms: in_segment.then_some(self.segment.peak_rms),
+            mean_rms: in_segment.then(|| self.segment.mean_rms()),
+            utterance: None,
+        }
+    }
+}
+
+// --- OFFLINE SEGMENTATION ---
+
+/// Chunk length used when replaying recorded audio through the live state machine.
+const OFFLINE_CHUNK_MS: u32 = 20;
+
+/// A speech segment found in recorded audio by [`segment_wav`].
+#[napi(object)]
+pub struct VadSegment {
+    pub start_ms: f64,
+    pub end_ms: f64,
+    pub peak_rms: f64,
+    pub mean_rms: f64,
+    /// Pre-roll + speech + hangover audio, when `captureUtterances` is set.
+    pub utterance: Option<Buffer>,
+    pub utterance_start_ms: Option<f64>,
+}
+
+impl From<VadStep> for VadSegment {
+    fn from(step: VadStep) -> Self {
+        let event = VadEvent::from(step);
+        VadSegment {
+            start_ms: event.segment_start_ms.unwrap_or(0.0),
+            end_ms: event.segment_end_ms.unwrap_or(0.0),
+            peak_rms: event.peak_rms.unwrap_or(0.0),
+            mean_rms: event.mean_rms.unwrap_or(0.0),
+            utterance: event.utterance,
+            utterance_start_ms: event.utterance_start_ms,
+        }
+    }
+}
+
+/// Maps a WAV `fmt ` chunk to the decoder's format descriptor.
+fn wav_format(fmt: &[u8]) -> napi::Result<AudioFormat> {
+    let invalid = |reason: String| napi::Error::new(napi::Status::InvalidArg, reason);
+    if fmt.len() < 16 {
+        return Err(invalid("Truncated WAV fmt chunk".to_string()));
+    }
+    let mut tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+    let channels = u16::from_le_bytes([fmt[2], fmt[3]]) as u32;
+    let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+    let bits = u16::from_le_bytes([fmt[14], fmt[15]]);
+    // WAVE_FORMAT_EXTENSIBLE carries the real format tag in its sub-format GUID.
+    if tag == 0xFFFE && fmt.len() >= 26 {
+        tag = u16::from_le_bytes([fmt[24], fmt[25]]);
+    }
+    let encoding = match (tag, bits) {
+        (1, 8) => "u8",
+        (1, 16) => "s16le",
+        (1, 24) => "s24le",
+        (1, 32) => "s32le",
+        (3, 32) => "f32le",
+        (6, 8) => "alaw",
+        (7, 8) => "mulaw",
+        _ => return Err(invalid(format!("Unsupported WAV format tag {} with {} bits", tag, bits))),
+    };
+    Ok(AudioFormat { encoding: encoding.to_string(), channels, sample_rate })
+}
+
+/// Locates the format descriptor and sample data of a RIFF/WAVE buffer.
+fn parse_wav(bytes: &[u8]) -> napi::Result<(AudioFormat, &[u8])> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(napi::Error::new(napi::Status::InvalidArg, "Not a RIFF/WAVE file"));
+    }
+    let mut format = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = u32::from_le_bytes([bytes[pos + 4], bytes[pos + 5], bytes[pos + 6], bytes[pos + 7]]) as usize;
+        let body = pos + 8;
+        // Streaming writers leave sizes unset; clamp to what is actually present.
+        let end = body.saturating_add(size).min(bytes.len());
+        match id {
+            b"fmt " => format = Some(wav_format(&bytes[body..end])?),
+            b"data" => {
+                let format = format.ok_or_else(|| {
+                    napi::Error::new(napi::Status::InvalidArg, "WAV data chunk precedes fmt chunk")
+                })?;
+                return Ok((format, &bytes[body..end]));
+            }
+            _ => {}
+        }
+        // Chunks are word-aligned.
+        pos = end.saturating_add(size & 1);
+    }
+    Err(napi::Error::new(napi::Status::InvalidArg, "WAV file has no data chunk"))
+}
+
+/// Replays a RIFF/WAVE buffer through the [`VadEngine`] state machine and
+/// returns the `speech_end` step of every segment.
+fn segment_pcm(
+    bytes: &[u8],
+    threshold: f64,
+    silence_timeout_ms: u32,
+    options: VadOptions,
+) -> napi::Result<Vec<VadStep>> {
+    let (format, data) = parse_wav(bytes)?;
+    let frame_bytes = SampleEncoding::parse(&format.encoding).map_or(1, |e| e.bytes_per_sample())
+        * format.channels.max(1) as usize;
+    let chunk_bytes = (format.sample_rate as usize * OFFLINE_CHUNK_MS as usize / 1000).max(1) * frame_bytes;
+
+    let options = VadOptions { format: Some(format), ..options };
+    let mut engine = VadEngine::with_options(threshold, silence_timeout_ms, options)?;
+    let mut segments = Vec::new();
+    let mut collect = |event: VadStep| {
+        if event.kind == VadEventKind::SpeechEnd {
+            segments.push(event);
+        }
+    };
+    for chunk in data.chunks(chunk_bytes) {
+        collect(engine.step(chunk));
+    }
+    collect(engine.finish());
+    Ok(segments)
+}
+
+/// Background job behind [`segment_wav`]: file I/O and segmentation run on
+/// the libuv thread pool; only the result conversion touches the JS thread.
+pub struct SegmentWav {
+    input: Either<String, Buffer>,
+    threshold: f64,
+    silence_timeout_ms: u32,
+    options: VadOptions,
+}
+
+impl napi::Task for SegmentWav {
+    type Output = Vec<VadStep>;
+    type JsValue = Vec<VadSegment>;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        if is_panic_mode() {
+            return Ok(Vec::new());
+        }
+        let owned;
+        let bytes: &[u8] = match &self.input {
+            Either::A(path) => {
+                owned = std::fs::read(path).map_err(|err| {
+                    napi::Error::new(napi::Status::GenericFailure, format!("Failed to read {}: {}", path, err))
+                })?;
+                &owned
+            }
+            Either::B(buffer) => buffer,
+        };
+        segment_pcm(bytes, self.threshold, self.silence_timeout_ms, self.options.clone())
+    }
+
+    fn resolve(&mut self, _env: napi::Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(output.into_iter().map(VadSegment::from).collect())
+    }
+}
+
+/// Splits a recorded WAV file (path or buffer) into speech segments by
+/// replaying it through the same [`VadEngine`] state machine used live.
+/// The capture format comes from the WAV header; any `format` option is ignored.
+/// Runs off the JS thread; the buffer must not be modified until the promise settles.
+#[napi]
+pub fn segment_wav(
+    input: Either<String, Buffer>,
+    threshold: f64,
+    silence_timeout_ms: u32,
+    options: Option<VadOptions>,
+) -> AsyncTask<SegmentWav> {
+    AsyncTask::new(SegmentWav { input, threshold, silence_timeout_ms, options: options.unwrap_or_default() })
+}
+
+// --- END OFFLINE SEGMENTATION ---
+
+// --- PITCH ESTIMATION ---
+
+/// Pitch search range covering adult and child speakers, in Hz.
+const PITCH_RANGE_HZ: (f64, f64) = (70.0, 400.0);
+/// Minimum normalized autocorrelation for a frame to count as voiced.
+const PITCH_MIN_CLARITY: f64 = 0.5;
+/// Audio kept for pitch analysis: two periods of the lowest pitch.
+const PITCH_WINDOW_MS: u32 = 40;
+
+/// A correlation peak within this share of the strongest one is taken as the
+/// period. Multiples of the period correlate almost as well as the period
+/// itself, so picking the global maximum would lock onto sub-harmonics.
+const PITCH_PEAK_RATIO: f64 = 0.9;
+
+/// Estimates the fundamental frequency of `frame` by normalized
+/// autocorrelation, taking the first strong peak (McLeod pitch method).
+/// Returns `None` for unvoiced or too-short frames.
+fn estimate_pitch(frame: &[f32], sample_rate: u32) -> Option<f64> {
+    let min_lag = (sample_rate as f64 / PITCH_RANGE_HZ.1).floor() as usize;
+    let max_lag = (sample_rate as f64 / PITCH_RANGE_HZ.0).ceil() as usize;
+    if min_lag < 2 || frame.len() < max_lag * 2 {
+        return None;
+    }
+    let mean = frame.iter().sum::<f32>() / frame.len() as f32;
+    let centered: Vec<f64> = frame.iter().map(|&s| (s - mean) as f64).collect();
+
+    // One lag of margin on each side so peaks at the range edges are seen.
+    let clarity: Vec<f64> = (min_lag - 1..=max_lag + 1)
+        .map(|lag| {
+            let head = &centered[..centered.len() - lag];
+            let tail = &centered[lag..];
+            let (mut cross, mut e_head, mut e_tail) = (0.0, 0.0, 0.0);
+            for (a, b) in head.iter().zip(tail) {
+                cross += a * b;
+                e_head += a * a;
+                e_tail += b * b;
+            }
+            if e_head <= 0.0 || e_tail <= 0.0 { 0.0 } else { cross / (e_head * e_tail).sqrt() }
+        })
+        .collect();
+
+    let peaks: Vec<usize> = (1..clarity.len() - 1)
+        .filter(|&i| clarity[i] > 0.0 && clarity[i] >= clarity[i - 1] && clarity[i] > clarity[i + 1])
+        .collect();
+    let strongest = peaks.iter().map(|&i| clarity[i]).fold(0.0, f64::max);
+    if strongest < PITCH_MIN_CLARITY {
+        return None;
+    }
+    let i = peaks.into_iter().find(|&i| clarity[i] >= strongest * PITCH_PEAK_RATIO)?;
+
+    // Parabolic interpolation between neighbouring lags.
+    let (left, mid, right) = (clarity[i - 1], clarity[i], clarity[i + 1]);
+    let curvature = left - 2.0 * mid + right;
+    let offset = if curvature < 0.0 { 0.5 * (left - right) / curvature } else { 0.0 };
+    let lag = (min_lag - 1 + i) as f64 + offset;
+    Some(sample_rate as f64 / lag)
+}
+
+/// Rolling analysis window feeding [`estimate_pitch`] from arbitrary chunk sizes.
+struct PitchTracker {
+    window: Vec<f32>,
+    capacity: usize,
+}
+
+impl PitchTracker {
+    fn new() -> Self {
+        let capacity = ms_to_samples(PITCH_WINDOW_MS);
+        PitchTracker { window: Vec::with_capacity(capacity * 2), capacity }
+    }
+
+    fn push(&mut self, samples: &[f32]) -> Option<f64> {
+        self.window.extend_from_slice(samples);
+        if self.window.len() > self.capacity {
+            let excess = self.window.len() - self.capacity;
+            self.window.drain(..excess);
+        }
+        estimate_pitch(&self.window, VAD_SAMPLE_RATE)
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// Pitch change in semitones from `from_hz` to `to_hz`.
+fn semitones(from_hz: f64, to_hz: f64) -> f64 {
+    12.0 * (to_hz / from_hz).log2()
+}
+
+// --- END PITCH ESTIMATION ---
+
+/// Phrases shorter than this are too brief to acknowledge.
+const CUE_HOLD_MIN_PHRASE_MS: f64 = 600.0;
+/// Syllables per second above which the speaker is rushing to hold the floor.
+const CUE_HOLD_MAX_SYLLABLE_RATE: f64 = 7.0;
+/// Conversational speech rate, in syllables per second.
+const CUE_TYPICAL_SYLLABLE_RATE: f64 = 4.0;
+/// Upward pitch excursion (semitones) that signals surprise-worthy content.
+const CUE_SURPRISE_PITCH_RISE: f64 = 5.0;
+/// Peak-to-mean energy ratio that signals emphatic delivery.
+const CUE_SURPRISE_EMPHASIS: f64 = 2.5;
+/// Final pitch fall (semitones) of an assertion that invites agreement.
+const CUE_AGREEMENT_MIN_FALL: f64 = 4.0;
+/// Assertions shorter than this get a continuer rather than agreement.
+const CUE_AGREEMENT_MIN_PHRASE_MS: f64 = 1_500.0;
+
+/// Kind of acknowledgement that fits a yield point.
+#[napi(string_enum = "snake_case")]
+#[derive(PartialEq, Debug)]
+pub enum BackchannelCueKind {
+    /// Between yield points.
+    None,
+    /// A minimal "mm-hm".
+    Continuer,
+    Agreement,
+    Surprise,
+    /// Stay silent.
+    Hold,
+}
+
+/// Acknowledgement suggested by [`BackchannelEngine::classify_chunk`].
+#[napi(object)]
+pub struct BackchannelCue {
+    pub kind: BackchannelCueKind,
+    /// Confidence in `kind`, from 0 to 1.
+    pub confidence: f64,
+    pub pause_ms: f64,
+    /// Syllables per second of voiced audio in the phrase.
+    pub speech_rate: f64,
+    /// Fall from the phrase pitch to its final syllables (positive = falling).
+    pub pitch_fall_semitones: f64,
+    /// Largest pitch rise of the phrase in time order (0 when it only falls).
+    pub pitch_rise_semitones: f64,
+}
+
+/// Prosodic summary of the phrase preceding a pause.
+#[derive(Default)]
+struct PhraseStats {
+    pitches: Vec<f64>,
+    voiced_samples: u64,
+    syllables: u32,
+    energy_sum: f64,
+    energy_peak: f64,
+    chunks: u32,
+    prev_rms: f64,
+    rising: bool,
+}
+
+impl PhraseStats {
+    fn add(&mut self, rms: f64, pitch: Option<f64>, samples: u64) {
+        if let Some(hz) = pitch {
+            self.pitches.push(hz);
+        }
+        self.voiced_samples += samples;
+        self.energy_sum += rms;
+        self.energy_peak = self.energy_peak.max(rms);
+        self.chunks += 1;
+        // Syllable nuclei show up as rise-then-fall peaks of the energy envelope.
+        if rms > self.prev_rms * 1.2 {
+            self.rising = true;
+        } else if self.rising && rms < self.prev_rms * 0.85 {
+            self.syllables += 1;
+            self.rising = false;
+        }
+        self.prev_rms = rms;
+    }
+
+    /// Counts the last syllable, which is cut off by the pause rather than a dip.
+    fn close(&mut self) {
+        if self.rising {
+            self.syllables += 1;
+            self.rising = false;
+        }
+        self.prev_rms = 0.0;
+    }
+
+    fn speech_rate(&self) -> f64 {
+        let seconds = samples_to_ms(self.voiced_samples) / 1000.0;
+        if seconds > 0.0 { self.syllables as f64 / seconds } else { 0.0 }
+    }
+
+    /// Compares the final syllables of the phrase with the phrase as a whole.
+    fn pitch_fall(&self) -> Option<f64> {
+        const TAIL: usize = 3;
+        if self.pitches.len() < TAIL * 2 {
+            return None;
+        }
+        let (body, tail) = self.pitches.split_at(self.pitches.len() - TAIL);
+        let body_mean = body.iter().sum::<f64>() / body.len() as f64;
+        let tail_mean = tail.iter().sum::<f64>() / tail.len() as f64;
+        Some(-semitones(body_mean, tail_mean))
+    }
+
+    /// Largest upward excursion in time order: from the lowest pitch so far
+    /// to a later peak, on a 3-point median of the contour so single-frame
+    /// octave errors do not count. A falling phrase scores 0.
+    fn pitch_rise(&self) -> f64 {
+        let mut low = f64::INFINITY;
+        let mut rise = 0.0f64;
+        for window in self.pitches.windows(3) {
+            let mut sorted = [window[0], window[1], window[2]];
+            sorted.sort_by(|a, b| a.total_cmp(b));
+            low = low.min(sorted[1]);
+            rise = rise.max(semitones(low, sorted[1]));
+        }
+        rise
+    }
+
+    fn emphasis(&self) -> f64 {
+        if self.chunks == 0 || self.energy_sum <= 0.0 {
+            return 0.0;
+        }
+        self.energy_peak / (self.energy_sum / self.chunks as f64)
+    }
+}
+
+/// Fixed-capacity ring of recent energy values with running mean and
+/// variance, so each update is O(1) regardless of the window size.
+struct EnergyWindow {
+    values: Vec<f64>,
+    capacity: usize,
+    head: usize,
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl EnergyWindow {
+    fn new(capacity: usize) -> Self {
+        EnergyWindow { values: Vec::with_capacity(capacity), capacity, head: 0, sum: 0.0, sum_sq: 0.0 }
+    }
+
+    fn push(&mut self, value: f64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.values.len() < self.capacity {
+            self.values.push(value);
+        } else {
+            let old = std::mem::replace(&mut self.values[self.head], value);
+            self.sum -= old;
+            self.sum_sq -= old * old;
+            self.head = (self.head + 1) % self.capacity;
+        }
+        self.sum += value;
+        self.sum_sq += value * value;
+        if self.head == 0 && self.values.len() == self.capacity {
+            // Re-sum once per lap so floating-point drift cannot accumulate.
+            self.sum = self.values.iter().sum();
+            self.sum_sq = self.values.iter().map(|v| v * v).sum();
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.capacity > 0 && self.values.len() == self.capacity
+    }
+
+    fn mean(&self) -> f64 {
+        if self.values.is_empty() { 0.0 } else { self.sum / self.values.len() as f64 }
+    }
+
+    fn variance(&self) -> f64 {
+        if self.values.is_empty() {
+            return 0.0;
+        }
+        let mean = self.mean();
+        (self.sum_sq / self.values.len() as f64 - mean * mean).max(0.0)
+    }
+
+    /// Changes the capacity, keeping the most recent values.
+    fn resize(&mut self, capacity: usize) {
+        let mut ordered: Vec<f64> = self.values[self.head..].iter().chain(&self.values[..self.head]).copied().collect();
+        if ordered.len() > capacity {
+            ordered.drain(..ordered.len() - capacity);
+        }
+        *self = EnergyWindow::new(capacity);
+        for value in ordered {
+            self.push(value);
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = EnergyWindow::new(self.capacity);
+    }
+}
+
+/// Tunable parameters for [`BackchannelEngine`], accepted at construction by
+/// `with_options` and at runtime by `set_options`. Unset fields keep their
+/// current value.
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct BackchannelOptions {
+    /// Number of recent energy values averaged. Default: 10.
+    pub history_limit: Option<u32>,
+    /// Minimum time between signals. Default: 3000 ms.
+    pub cooldown_ms: Option<u32>,
+    /// Consecutive active chunks required before a pause becomes eligible. Default: 0.
+    pub min_speech_chunks: Option<u32>,
+    /// A pause starts when energy falls below this share of the recent average. Default: 0.3.
+    pub drop_ratio: Option<f64>,
+    /// RMS above which a chunk counts as active speech. Default: 500.
+    pub min_active_energy: Option<f64>,
+    /// Capture format for `processChunk`. Defaults to little-endian mono i16 at 16 kHz.
+    pub format: Option<AudioFormat>,
+    /// Shortest pause that can host a backchannel. Default: 200 ms.
+    pub min_pause_ms: Option<u32>,
+    /// Longest pause that still counts as mid-turn; beyond it the phrase is over. Default: 1200 ms.
+    pub max_pause_ms: Option<u32>,
+    /// Required fall from the phrase pitch to its final syllables. Default: 2 semitones.
+    pub pitch_fall_semitones: Option<f64>,
+    /// Only fire on falling intonation; otherwise pause and energy suffice. Default: true.
+    pub require_falling_pitch: Option<bool>,
+}
+
+/// [PT] Motor de Heurísticas de Backchannel (Escuta Ativa).
+/// Detecta janelas de oportunidade rítmicas para emitir reconhecimentos vocais curtos.
+///
+/// `process_energy` works on pre-computed RMS values and wall-clock cooldown.
+/// `process_chunk` analyzes raw PCM on the sample clock and additionally
+/// requires a pause of natural length and falling intonation at the phrase
+/// end, so acknowledgements land at turn-yield points instead of any breath.
+#[napi]
+pub struct BackchannelEngine {
+    energy: EnergyWindow,
+    last_signal_time: Option<Instant>,
+    cooldown_ms: u64,
+    min_speech_chunks: u32,
+    speech_run: u32,
+    run_eligible: bool,
+    drop_ratio: f64,
+    min_active_energy: f64,
+    min_pause_ms: f64,
+    max_pause_ms: f64,
+    pitch_fall_semitones: f64,
+    require_falling_pitch: bool,
+    format: AudioFormat,
+    decoder: PcmDecoder,
+    pitch: PitchTracker,
+    stream_position: u64,
+    last_signal_position: Option<u64>,
+    phrase: PhraseStats,
+    last_pitch_hz: Option<f64>,
+    pause_start: Option<u64>,
+    pause_drop: bool,
+    pause_signaled: bool,
+}
+
+#[napi]
+impl BackchannelEngine {
+    #[napi(constructor)]
+    pub fn new(history_limit: u32, cooldown_ms: u32) -> napi::Result<Self> {
+        Self::with_options(BackchannelOptions {
+            history_limit: Some(history_limit),
+            cooldown_ms: Some(cooldown_ms),
+            ..Default::default()
+        })
+    }
+
+    /// Creates an engine with tuned heuristics and capture format.
+    #[napi(factory)]
+    pub fn with_options(options: BackchannelOptions) -> napi::Result<Self> {
+        let format = AudioFormat::default_pcm16();
+        let mut engine = BackchannelEngine {
+            energy: EnergyWindow::new(options.history_limit.unwrap_or(10) as usize),
+            last_signal_time: None,
+            cooldown_ms: 3_000,
+            min_speech_chunks: 0,
+            speech_run: 0,
+            run_eligible: true,
+            drop_ratio: 0.3,
+            min_active_energy: 500.0,
+            min_pause_ms: 200.0,
+            max_pause_ms: 1_200.0,
+            pitch_fall_semitones: 2.0,
+            require_falling_pitch: true,
+            decoder: PcmDecoder::new(&format, VAD_SAMPLE_RATE)?,
+            format,
+            pitch: PitchTracker::new(),
+            stream_position: 0,
+            last_signal_position: None,
+            phrase: PhraseStats::default(),
+            last_pitch_hz: None,
+            pause_start: None,
+            pause_drop: false,
+            pause_signaled: false,
+        };
+        engine.set_options(options)?;
+        Ok(engine)
+    }
+
+    /// Updates heuristics at runtime. Only the fields present are changed;
+    /// a new `format` restarts PCM decoding.
+    #[napi]
+    pub fn set_options(&mut self, options: BackchannelOptions) -> napi::Result<()> {
+        let invalid = |name: &str, value: f64| {
+            napi::Error::new(napi::Status::InvalidArg, format!("Invalid backchannel {}: {}", name, value))
+        };
+        if options.history_limit == Some(0) {
+            return Err(invalid("historyLimit", 0.0));
+        }
+        if let Some(ratio) = options.drop_ratio {
+            if !(ratio > 0.0 && ratio <= 1.0) {
+                return Err(invalid("dropRatio", ratio));
+            }
+        }
+        if let Some(energy) = options.min_active_energy {
+            if !energy.is_finite() || energy < 0.0 {
+                return Err(invalid("minActiveEnergy", energy));
+            }
+        }
+        if let Some(fall) = options.pitch_fall_semitones {
+            if !fall.is_finite() {
+                return Err(invalid("pitchFallSemitones", fall));
+            }
+        }
+        let min_pause = options.min_pause_ms.map_or(self.min_pause_ms, |ms| ms as f64);
+        let max_pause = options.max_pause_ms.map_or(self.max_pause_ms, |ms| ms as f64);
+        if min_pause > max_pause {
+            return Err(invalid("minPauseMs", min_pause));
+        }
+        // Everything is validated before anything is applied.
+        if let Some(format) = options.format {
+            self.decoder = PcmDecoder::new(&format, VAD_SAMPLE_RATE)?;
+            self.format = format;
+            self.pitch.reset();
+        }
+        if let Some(limit) = options.history_limit {
+            self.energy.resize(limit as usize);
+        }
+        if let Some(cooldown) = options.cooldown_ms {
+            self.cooldown_ms = cooldown as u64;
+        }
+        if let Some(chunks) = options.min_speech_chunks {
+            self.min_speech_chunks = chunks;
+        }
+        if let Some(ratio) = options.drop_ratio {
+            self.drop_ratio = ratio;
+        }
+        if let Some(energy) = options.min_active_energy {
+            self.min_active_energy = energy;
+        }
+        self.min_pause_ms = min_pause;
+        self.max_pause_ms = max_pause;
+        if let Some(fall) = options.pitch_fall_semitones {
+            self.pitch_fall_semitones = fall;
+        }
+        if let Some(require) = options.require_falling_pitch {
+            self.require_falling_pitch = require;
+        }
+        Ok(())
+    }
+
+    /// Returns the complete current configuration.
+    #[napi]
+    pub fn get_options(&self) -> BackchannelOptions {
+        BackchannelOptions {
+            history_limit: Some(self.energy.capacity as u32),
+            cooldown_ms: Some(self.cooldown_ms as u32),
+            min_speech_chunks: Some(self.min_speech_chunks),
+            drop_ratio: Some(self.drop_ratio),
+            min_active_energy: Some(self.min_active_energy),
+            format: Some(self.format.clone()),
+            min_pause_ms: Some(self.min_pause_ms as u32),
+            max_pause_ms: Some(self.max_pause_ms as u32),
+            pitch_fall_semitones: Some(self.pitch_fall_semitones),
+            require_falling_pitch: Some(self.require_falling_pitch),
+        }
+    }
+
+    /// Running mean of the energy window.
+    #[napi(getter)]
+    pub fn energy_mean(&self) -> f64 {
+        self.energy.mean()
+    }
+
+    /// Running standard deviation of the energy window.
+    #[napi(getter)]
+    pub fn energy_std_dev(&self) -> f64 {
+        self.energy.variance().sqrt()
+    }
+
+    #[napi]
+    pub fn process_energy(&mut self, rms: f64) -> bool {
+        if is_panic_mode() { return false; }
+
+        let eligible = self.track_speech_run(rms);
+        self.push_energy(rms);
+        if !eligible {
+            return false;
+        }
+
+        // Só disparar se o cooldown expirou
+        if self.last_signal_time.is_some_and(|at| at.elapsed().as_millis() < self.cooldown_ms as u128) {
+            return false;
+        }
+
+        // Heurística: Queda súbita de energia após um período de atividade (indica pausa natural)
+        if self.energy_dropped(rms) {
+            self.last_signal_time = Some(Instant::now());
+            return true;
+        }
+
+        false
+    }
+
+    /// Analyzes raw PCM and returns `true` at a natural turn-yield point:
+    /// a sudden energy drop, a pause within `[minPauseMs, maxPauseMs]` and,
+    /// unless disabled, falling intonation at the end of the phrase.
+    #[napi]
+    pub fn process_chunk(&mut self, chunk: Buffer) -> bool {
+        self.step(&chunk).is_some()
+    }
+
+    /// Like `process_chunk`, but also says which acknowledgement fits the
+    /// yield point: `continuer` ("mm-hm"), `agreement`, `surprise` or `hold`
+    /// (stay silent). Returns kind `none` between yield points. Use either
+    /// this or `process_chunk` on a given stream, not both.
+    #[napi]
+    pub fn classify_chunk(&mut self, chunk: Buffer) -> BackchannelCue {
+        self.cue(&chunk)
+    }
+
+    fn cue(&mut self, chunk: &[u8]) -> BackchannelCue {
+        match self.step(chunk) {
+            Some(phrase) => self.classify(&phrase),
+            None => BackchannelCue {
+                kind: BackchannelCueKind::None,
+                confidence: 0.0,
+                pause_ms: self.pause_ms(),
+                speech_rate: 0.0,
+                pitch_fall_semitones: 0.0,
+                pitch_rise_semitones: 0.0,
+            },
+        }
+    }
+
+    /// Advances the PCM state machine; returns the finished phrase at a yield point.
+    fn step(&mut self, chunk: &[u8]) -> Option<PhraseStats> {
+        if is_panic_mode() { return None; }
+
+        let samples = self.decoder.decode(chunk);
+        if samples.is_empty() {
+            return None;
+        }
+        let rms = (samples.iter().map(|&s| (s as f64) * (s as f64)).sum::<f64>() / samples.len() as f64).sqrt();
+        let pitch = self.pitch.push(&samples);
+        let chunk_start = self.stream_position;
+        self.stream_position += samples.len() as u64;
+
+        let eligible = self.track_speech_run(rms);
+        if rms > self.min_active_energy {
+            self.push_energy(rms);
+            self.pause_start = None;
+            self.last_pitch_hz = pitch;
+            self.phrase.add(rms, pitch, samples.len() as u64);
+            return None;
+        }
+
+        if self.pause_start.is_none() {
+            self.pause_start = Some(chunk_start);
+            self.pause_drop = false;
+            self.phrase.close();
+            self.pause_signaled = false;
+        }
+        // Phrase endings often taper, so the drop may only show a few chunks
+        // into the pause. It is measured against the history preceding each chunk.
+        self.pause_drop |= self.energy_dropped(rms);
+        self.push_energy(rms);
+
+        let pause_ms = samples_to_ms(self.stream_position - self.pause_start.unwrap_or(chunk_start));
+        if pause_ms > self.max_pause_ms {
+            // The phrase is over; the next one builds its own contour.
+            self.phrase = PhraseStats::default();
+            return None;
+        }
+        let cooled = self.last_signal_position.is_none_or(|pos| {
+            samples_to_ms(self.stream_position - pos) >= self.cooldown_ms as f64
+        });
+        let falling = self.phrase.pitch_fall().is_some_and(|fall| fall >= self.pitch_fall_semitones);
+        if !self.pause_signaled
+            && cooled
+            && eligible
+            && pause_ms >= self.min_pause_ms
+            && self.pause_drop
+            && (falling || !self.require_falling_pitch)
+        {
+            self.pause_signaled = true;
+            self.last_signal_position = Some(self.stream_position);
+            return Some(std::mem::take(&mut self.phrase));
+        }
+        None
+    }
+
+    fn classify(&self, phrase: &PhraseStats) -> BackchannelCue {
+        let rate = phrase.speech_rate();
+        let fall = phrase.pitch_fall().unwrap_or(0.0);
+        let rise = phrase.pitch_rise();
+        let emphasis = phrase.emphasis();
+        let duration_ms = samples_to_ms(phrase.voiced_samples);
+
+        // Each rule scores how far past its trigger the phrase is.
+        let (kind, confidence) = if duration_ms < CUE_HOLD_MIN_PHRASE_MS || rate > CUE_HOLD_MAX_SYLLABLE_RATE {
+            let short = (CUE_HOLD_MIN_PHRASE_MS - duration_ms) / CUE_HOLD_MIN_PHRASE_MS;
+            let rushed = (rate - CUE_HOLD_MAX_SYLLABLE_RATE) / CUE_HOLD_MAX_SYLLABLE_RATE;
+            (BackchannelCueKind::Hold, 0.5 + 0.5 * short.max(rushed))
+        } else if rise >= CUE_SURPRISE_PITCH_RISE || emphasis >= CUE_SURPRISE_EMPHASIS {
+            let excursion = (rise - CUE_SURPRISE_PITCH_RISE) / CUE_SURPRISE_PITCH_RISE;
+            let stress = (emphasis - CUE_SURPRISE_EMPHASIS) / CUE_SURPRISE_EMPHASIS;
+            (BackchannelCueKind::Surprise, 0.5 + 0.5 * excursion.max(stress))
+        } else if fall >= CUE_AGREEMENT_MIN_FALL && duration_ms >= CUE_AGREEMENT_MIN_PHRASE_MS {
+            let assertive = (fall - CUE_AGREEMENT_MIN_FALL) / CUE_AGREEMENT_MIN_FALL;
+            (BackchannelCueKind::Agreement, 0.6 + 0.4 * assertive)
+        } else {
+            // Mild, regular phrase ends; a minimal continuer is the safe default.
+            let regular = 1.0 - (rate - CUE_TYPICAL_SYLLABLE_RATE).abs() / CUE_TYPICAL_SYLLABLE_RATE;
+            (BackchannelCueKind::Continuer, 0.5 + 0.4 * regular)
+        };
+
+        BackchannelCue {
+            kind,
+            confidence: confidence.clamp(0.0, 1.0),
+            pause_ms: self.pause_ms(),
+            speech_rate: rate,
+            pitch_fall_semitones: fall,
+            pitch_rise_semitones: rise,
+        }
+    }
+
+    /// Pitch of the last voiced chunk seen by `process_chunk`, in Hz (0 when unvoiced).
+    #[napi(getter)]
+    pub fn last_pitch_hz(&self) -> f64 {
+        self.last_pitch_hz.unwrap_or(0.0)
+    }
+
+    /// Length of the current pause seen by `process_chunk`, in milliseconds.
+    #[napi(getter)]
+    pub fn pause_ms(&self) -> f64 {
+        self.pause_start.map_or(0.0, |start| samples_to_ms(self.stream_position - start))
+    }
+
+    /// Discards PCM decoding state, pitch contour, energy history, the
+    /// current pause and the signal cooldown.
+    #[napi]
+    pub fn reset(&mut self) {
+        self.energy.clear();
+        self.speech_run = 0;
+        self.run_eligible = true;
+        self.decoder.reset();
+        self.pitch.reset();
+        self.stream_position = 0;
+        self.last_signal_time = None;
+        self.last_signal_position = None;
+        self.phrase = PhraseStats::default();
+        self.last_pitch_hz = None;
+        self.pause_start = None;
+        self.pause_drop = false;
+        self.pause_signaled = false;
+    }
+
+    fn push_energy(&mut self, rms: f64) {
+        // Manter histórico de energia
+        self.energy.push(rms);
+    }
+
+    /// Counts consecutive active values; a pause is only eligible when the
+    /// run that preceded it reached `min_speech_chunks`.
+    fn track_speech_run(&mut self, rms: f64) -> bool {
+        if rms > self.min_active_energy {
+            self.speech_run = self.speech_run.saturating_add(1);
+            self.run_eligible = true;
+        } else if self.speech_run > 0 {
+            self.run_eligible = self.speech_run >= self.min_speech_chunks;
+            self.speech_run = 0;
+        }
+        self.run_eligible
+    }
+
+    fn energy_dropped(&self, rms: f64) -> bool {
+        if !self.energy.is_full() {
+            return false;
+        }
+        let avg_energy = self.energy.mean();
+
+        // Se a energia atual está bem abaixo da média do histórico recente,
+        // e a média era significativamente alta (ex: voz ativa)
+        rms < avg_energy * self.drop_ratio && avg_energy > self.min_active_energy
+    }
+
+}
+
+// --- TURN-TAKING ---
+
+/// Silence after which the turn is over regardless of the other cues.
+const TURN_DEFAULT_MAX_SILENCE_MS: u32 = 1_500;
+/// Silence span over which the silence cue grows by one logit unit.
+const TURN_SILENCE_SCALE_MS: f64 = 250.0;
+/// Bias keeping the probability low while no cue is present.
+const TURN_LOGIT_BIAS: f64 = -3.5;
+/// Trailing words that signal the speaker intends to continue.
+const TURN_CONTINUATION_WORDS: &[&str] = &[
+    "and", "but", "or", "so", "because", "um", "uh", "like", "then", "with",
+    "e", "mas", "ou", "porque", "então", "entao", "tipo", "que", "de", "com",
+];
+
+/// Parameters for [`TurnTakingEngine`].
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct TurnTakingOptions {
+    /// Capture format. Defaults to little-endian mono i16 at 16 kHz.
+    pub format: Option<AudioFormat>,
+    /// RMS above which a chunk counts as speech. Default: 500.
+    pub min_active_energy: Option<f64>,
+    /// Silence that ends the turn on its own. Default: 1500 ms.
+    pub max_silence_ms: Option<u32>,
+    /// Probability at which `endOfTurn` is reported. Default: 0.7.
+    pub decision_threshold: Option<f64>,
+}
+
+/// Per-chunk output of [`TurnTakingEngine::process_chunk`].
+#[napi(object)]
+pub struct TurnPrediction {
+    /// Probability (0-1) that the user has finished their turn.
+    pub probability: f64,
+    /// `probability` reached the decision threshold.
+    pub end_of_turn: bool,
+    pub is_speaking: bool,
+    pub silence_ms: f64,
+    /// How far the final syllables fell below the phrase energy (0-1).
+    pub energy_decay: f64,
+    /// Pitch fall at the phrase end, in semitones (positive = falling).
+    pub pitch_drop_semitones: f64,
+    /// Transcript cue: positive for a completed sentence, negative for a trailing connective.
+    pub hint_score: f64,
+}
+
+/// Scores how strongly a partial transcript suggests the turn is complete.
+fn transcript_hint_score(text: &str) -> f64 {
+    let text = text.trim_end();
+    if text.is_empty() {
+        return 0.0;
+    }
+    if text.ends_with('?') {
+        return 1.5;
+    }
+    if text.ends_with(['.', '!', '…']) {
+        return 1.0;
+    }
+    if text.ends_with([',', ';', ':', '-']) {
+        return -1.5;
+    }
+    let last_word = text
+        .rsplit(char::is_whitespace)
+        .next()
+        .unwrap_or("")
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase();
+    if TURN_CONTINUATION_WORDS.contains(&last_word.as_str()) {
+        -1.5
+    } else {
+        0.0
+    }
+}
+
+/// [PT] Motor de previsão de fim de turno.
+///
+/// Estimates, per chunk, the probability that the user has finished their
+/// turn by combining silence duration, energy decay and pitch drop at the
+/// phrase end, and an optional transcript-ending hint, so the agent can
+/// respond without a fixed silence wait.
+#[napi]
+pub struct TurnTakingEngine {
+    decoder: PcmDecoder,
+    pitch: PitchTracker,
+    phrase: PhraseStats,
+    tail_energy: EnergyWindow,
+    min_active_energy: f64,
+    max_silence_ms: f64,
+    decision_threshold: f64,
+    stream_position: u64,
+    silence_start: Option<u64>,
+    hint: Option<String>,
+}
+
+#[napi]
+impl TurnTakingEngine {
+    #[napi(constructor)]
+    pub fn new(options: Option<TurnTakingOptions>) -> napi::Result<Self> {
+        let options = options.unwrap_or_default();
+        let invalid = |name: &str, value: f64| {
+            napi::Error::new(napi::Status::InvalidArg, format!("Invalid turn-taking {}: {}", name, value))
+        };
+        if let Some(energy) = options.min_active_energy {
+            if !energy.is_finite() || energy < 0.0 {
+                return Err(invalid("minActiveEnergy", energy));
+            }
+        }
+        if let Some(threshold) = options.decision_threshold {
+            if !threshold.is_finite() {
+                return Err(invalid("decisionThreshold", threshold));
+            }
+        }
+        let format = options.format.unwrap_or_else(AudioFormat::default_pcm16);
+        Ok(TurnTakingEngine {
+            decoder: PcmDecoder::new(&format, VAD_SAMPLE_RATE)?,
+            pitch: PitchTracker::new(),
+            phrase: PhraseStats::default(),
+            // The final syllables: about 60 ms at typical chunk sizes.
+            tail_energy: EnergyWindow::new(3),
+            min_active_energy: options.min_active_energy.unwrap_or(500.0),
+            max_silence_ms: options.max_silence_ms.unwrap_or(TURN_DEFAULT_MAX_SILENCE_MS) as f64,
+            decision_threshold: options.decision_threshold.unwrap_or(0.7).clamp(0.0, 1.0),
+            stream_position: 0,
+            silence_start: None,
+            hint: None,
+        })
+    }
+
+    /// Processes a chunk and returns the current end-of-turn estimate.
+    /// `transcript_hint` is the latest partial transcript; it is kept until
+    /// replaced, and an empty string clears it.
+    #[napi]
+    pub fn process_chunk(&mut self, chunk: Buffer, transcript_hint: Option<String>) -> TurnPrediction {
+        self.predict(&chunk, transcript_hint)
+    }
+
+    /// Forgets the current phrase, silence and transcript hint.
+    #[napi]
+    pub fn reset(&mut self) {
+        self.decoder.reset();
+        self.pitch.reset();
+        self.phrase = PhraseStats::default();
+        self.tail_energy.clear();
+        self.stream_position = 0;
+        self.silence_start = None;
+        self.hint = None;
+    }
+
+    fn predict(&mut self, chunk: &[u8], transcript_hint: Option<String>) -> TurnPrediction {
+        if let Some(hint) = transcript_hint {
+            self.hint = (!hint.trim().is_empty()).then_some(hint);
+        }
+        if is_panic_mode() {
+            return self.prediction(false);
+        }
+
+        let samples = self.decoder.decode(chunk);
+        if samples.is_empty() {
+            return self.prediction(self.silence_start.is_none() && self.phrase.chunks > 0);
+        }
+        let rms = (samples.iter().map(|&s| (s as f64) * (s as f64)).sum::<f64>() / samples.len() as f64).sqrt();
+        let pitch = self.pitch.push(&samples);
+        let chunk_start = self.stream_position;
+        self.stream_position += samples.len() as u64;
+
+        if rms > self.min_active_energy {
+            if self.silence_start.take().is_some_and(|start| samples_to_ms(chunk_start - start) > self.max_silence_ms) {
+                // A new turn: the previous phrase no longer shapes the contour.
+                self.phrase = PhraseStats::default();
+                self.tail_energy.clear();
+            }
+            self.phrase.add(rms, pitch, samples.len() as u64);
+            self.tail_energy.push(rms);
+            return self.prediction(true);
+        }
+
+        if self.phrase.chunks > 0 {
+            self.silence_start.get_or_insert(chunk_start);
+        }
+        self.prediction(false)
+    }
+
+    fn prediction(&self, is_speaking: bool) -> TurnPrediction {
+        let silence_ms = self.silence_start.map_or(0.0, |start| samples_to_ms(self.stream_position - start));
+        let phrase_mean = if self.phrase.chunks > 0 { self.phrase.energy_sum / self.phrase.chunks as f64 } else { 0.0 };
+        let energy_decay = if phrase_mean > 0.0 { (1.0 - self.tail_energy.mean() / phrase_mean).clamp(0.0, 1.0) } else { 0.0 };
+        let pitch_drop = self.phrase.pitch_fall().unwrap_or(0.0);
+        let hint_score = self.hint.as_deref().map_or(0.0, transcript_hint_score);
+
+        let probability = if is_speaking || self.phrase.chunks == 0 {
+            0.0
+        } else if silence_ms >= self.max_silence_ms {
+            1.0
+        } else {
+            // Prosodic and lexical cues only shift the curve; silence drives it.
+            let logit = TURN_LOGIT_BIAS
+                + silence_ms / TURN_SILENCE_SCALE_MS
+                + 1.5 * energy_decay
+                + 0.3 * pitch_drop.clamp(-4.0, 8.0)
+                + hint_score;
+            1.0 / (1.0 + (-logit).exp())
+        };
+
+        TurnPrediction {
+            probability,
+            end_of_turn: probability >= self.decision_threshold,
+            is_speaking,
+            silence_ms,
+            energy_decay,
+            pitch_drop_semitones: pitch_drop,
+            hint_score,
+        }
+    }
+}
+
+// --- END TURN-TAKING ---
+
+// --- BARGE-IN DETECTION ---
+
+/// Parameters for [`BargeInDetector`].
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct BargeInOptions {
+    /// RMS level user speech must exceed while the assistant talks. Default: 1500.
+    pub threshold: Option<f64>,
+    /// Sustained user speech required to interrupt. Default: 500 ms.
+    pub interrupt_ms: Option<u32>,
+    /// Silence that closes a user burst. Default: 200 ms.
+    pub silence_timeout_ms: Option<u32>,
+    /// Options for the underlying VAD (format, spectral mode, echo reference, ...).
+    pub vad: Option<VadOptions>,
+}
+
+/// Barge-in state reported for each processed mic chunk.
+#[napi(string_enum = "snake_case")]
+#[derive(PartialEq, Debug)]
+pub enum BargeInEvent {
+    /// The assistant is not speaking.
+    Inactive,
+    Idle,
+    /// A user burst is under way but not yet decided.
+    Listening,
+    /// Reported once per burst, when it has lasted `interrupt_ms`.
+    Interrupt,
+    /// The user keeps talking after an interrupt.
+    Talking,
+    Backchannel,
+    Panic,
+}
+
+/// [PT] Detector de interrupção (barge-in) durante a fala do assistente.
+///
+/// Decides whether the user is genuinely interrupting the assistant (speech
+/// above a stricter threshold, sustained for `interrupt_ms`) or only
+/// backchanneling ("uh-huh"). Bursts that collapse early, as flagged by the
+/// backchannel energy-drop heuristic, or end before `interrupt_ms` are
+/// reported as `backchannel`.
+#[napi]
+pub struct BargeInDetector {
+    vad: VadEngine,
+    backchannel: BackchannelEngine,
+    interrupt_ms: f64,
+    assistant_speaking: bool,
+    // Bursts never start before playback began or before the last dismissed burst.
+    burst_floor_ms: f64,
+    burst_start_ms: Option<f64>,
+    interrupted: bool,
+}
+
+#[napi]
+impl BargeInDetector {
+    #[napi(constructor)]
+    pub fn new(options: Option<BargeInOptions>) -> napi::Result<Self> {
+        let options = options.unwrap_or_default();
+        let vad = VadEngine::with_options(
+            options.threshold.unwrap_or(1500.0),
+            options.silence_timeout_ms.unwrap_or(200),
+            options.vad.unwrap_or_default(),
+        )?;
+        Ok(BargeInDetector {
+            vad,
+            // A short history reacts within a few chunks of a burst collapsing.
+            backchannel: BackchannelEngine::new(4, 0)?,
+            interrupt_ms: options.interrupt_ms.unwrap_or(500) as f64,
+            assistant_speaking: false,
+            burst_floor_ms: 0.0,
+            burst_start_ms: None,
+            interrupted: false,
+        })
+    }
+
+    /// Marks the start or end of assistant playback. Barge-in is only
+    /// evaluated while the assistant is speaking.
+    #[napi]
+    pub fn set_assistant_speaking(&mut self, speaking: bool) {
+        if speaking && !self.assistant_speaking {
+            self.burst_floor_ms = self.vad.stream_time_ms();
+        }
+        self.assistant_speaking = speaking;
+        self.burst_start_ms = None;
+        self.interrupted = false;
+    }
+
+    /// Forwards assistant playback audio to the VAD's echo suppressor, at
+    /// playback time (see [`VadEngine::push_reference`]).
+    #[napi]
+    pub fn push_reference(&mut self, chunk: Buffer) {
+        self.vad.feed_reference(&chunk);
+    }
+
+    /// Duration of the current user burst in milliseconds (0 when none).
+    #[napi(getter)]
+    pub fn burst_ms(&self) -> f64 {
+        self.burst_start_ms.map_or(0.0, |start| self.vad.stream_time_ms() - start)
+    }
+
+    /// Processes mic audio and reports the barge-in state.
+    #[napi]
+    pub fn process_chunk(&mut self, chunk: Buffer) -> BargeInEvent {
+        self.step(&chunk)
+    }
+
+    fn step(&mut self, chunk: &[u8]) -> BargeInEvent {
+        let event = self.vad.step(chunk);
+        if event.kind == VadEventKind::Panic {
+            return BargeInEvent::Panic;
+        }
+        let energy_dropped = self.backchannel.process_energy(event.rms);
+
+        if !self.assistant_speaking {
+            return BargeInEvent::Inactive;
+        }
+
+        if !event.in_segment() {
+            let was_burst = self.burst_start_ms.take().is_some() && !self.interrupted;
+            self.interrupted = false;
+            return if was_burst && event.kind == VadEventKind::SpeechEnd {
+                BargeInEvent::Backchannel
+            } else {
+                BargeInEvent::Idle
+            };
+        }
+
+        if self.interrupted {
+            return BargeInEvent::Talking;
+        }
+        let voiced = event.kind != VadEventKind::Silencing;
+        let end = samples_to_ms(event.sample_offset + event.sample_count as u64);
+        let start = match self.burst_start_ms {
+            Some(start) => start,
+            None if voiced => {
+                let segment_start = event.segment_start_ms.unwrap_or(end);
+                *self.burst_start_ms.insert(segment_start.max(self.burst_floor_ms))
+            }
+            None => return BargeInEvent::Idle,
+        };
+        if voiced && end - start >= self.interrupt_ms {
+            self.interrupted = true;
+            return BargeInEvent::Interrupt;
+        }
+        if energy_dropped {
+            // The burst collapsed before becoming an interruption; any speech
+            // that follows is measured as a fresh burst.
+            self.burst_start_ms = None;
+            self.burst_floor_ms = end;
+            return BargeInEvent::Backchannel;
+        }
+        BargeInEvent::Listening
+    }
+}
+
+// --- END BARGE-IN DETECTION ---
+
+// --- LABELED METRICS ---
+
+/// Series per metric before new label sets are rejected (DoS protection).
+const DEFAULT_MAX_SERIES: usize = 50;
+/// Distinct metric names a single engine will register.
+const MAX_METRICS: usize = 256;
+const MAX_LABELS_PER_SERIES: usize = 16;
+const MAX_LABEL_VALUE_LEN: usize = 256;
+/// Label names the exposition formats reserve for buckets and quantiles.
+const RESERVED_LABEL_NAMES: &[&str] = &["le", "quantile"];
+/// Default histogram upper bounds, in milliseconds.
+const DEFAULT_LATENCY_BUCKETS: &[f64] = &[5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1_000.0, 2_500.0, 5_000.0, 10_000.0];
+
+/// Counter fed by [`MetricsEngine::record_tokens`].
+const TOKENS_METRIC: &str = "ratchet_tokens_total";
+/// Histogram fed by [`MetricsEngine::record_latency`].
+const LATENCY_METRIC: &str = "ratchet_latency_ms";
+
+/// Type of a [`MetricsEngine`] metric.
+#[napi(string_enum = "snake_case")]
+#[derive(PartialEq, Eq, Debug)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl MetricKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+            MetricKind::Histogram => "histogram",
+        }
+    }
+}
+
+/// Label pairs sorted by name, so `{a, b}` and `{b, a}` address the same series.
+type LabelSet = Vec<(String, String)>;
+
+fn is_valid_metric_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name.len() <= 128
+}
+
+fn label_set(labels: Option<HashMap<String, String>>) -> napi::Result<LabelSet> {
+    let mut set: LabelSet = labels.unwrap_or_default().into_iter().collect();
+    if set.len() > MAX_LABELS_PER_SERIES {
+        return Err(napi::Error::new(
+            napi::Status::InvalidArg,
+            format!("Too many labels: {} (max {})", set.len(), MAX_LABELS_PER_SERIES),
+        ));
+    }
+    for (name, value) in set.iter_mut() {
+        if !is_valid_metric_name(name) || name.starts_with("__") || RESERVED_LABEL_NAMES.contains(&name.as_str()) {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Invalid label name: {}", name),
+            ));
+        }
+        if value.len() > MAX_LABEL_VALUE_LEN {
+            let mut end = MAX_LABEL_VALUE_LEN;
+            while !value.is_char_boundary(end) {
+                end -= 1;
+            }
+            value.truncate(end);
+        }
+    }
+    set.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    Ok(set)
+}
+
+fn label_map(labels: &LabelSet) -> HashMap<String, String> {
+    labels.iter().cloned().collect()
+}
+
+/// Formats a sample value the way the exposition formats spell non-finite numbers.
+fn exposition_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Writes `name{labels,extra} value` as one exposition line.
+fn write_sample(out: &mut String, name: &str, labels: &LabelSet, extra: Option<(&str, &str)>, value: f64) {
+    out.push_str(name);
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .chain(extra)
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape_label_value(v)))
+        .collect();
+    if !pairs.is_empty() {
+        out.push('{');
+        out.push_str(&pairs.join(","));
+        out.push('}');
+    }
+    out.push(' ');
+    out.push_str(&exposition_value(value));
+    out.push('\n');
+}
+
+/// A sampled observation linked to the trace that produced it.
+#[derive(Clone, Copy)]
+struct Exemplar {
+    value: f64,
+    time_ms: f64,
+    trace_id: Option<[u8; 16]>,
+    span_id: Option<[u8; 8]>,
+}
+
+#[derive(Clone)]
+enum SeriesValue {
+    Scalar(f64),
+    /// Per-bucket (non-cumulative) counts; the last slot is the `+Inf` overflow.
+    /// `exemplars` keeps the latest exemplar per bucket and is not persisted.
+    Histogram { counts: Vec<u64>, sum: f64, count: u64, exemplars: Vec<Option<Exemplar>> },
+}
+
+struct Metric {
+    kind: MetricKind,
+    help: String,
+    buckets: Vec<f64>,
+    max_series: usize,
+    series: IndexMap<LabelSet, SeriesValue>,
+    /// Updates rejected because they would have opened a series past `max_series`.
+    dropped_series: u64,
+}
+
+impl Metric {
+    fn new(kind: MetricKind, help: String, buckets: Vec<f64>, max_series: usize) -> Self {
+        Metric { kind, help, buckets, max_series, series: IndexMap::new(), dropped_series: 0 }
+    }
+
+    /// Returns the series for `labels`, or `None` once the cardinality limit is hit.
+    fn series_mut(&mut self, labels: LabelSet) -> Option<(&mut SeriesValue, &[f64])> {
+        if self.series.len() >= self.max_series && !self.series.contains_key(&labels) {
+            self.dropped_series += 1;
+            return None;
+        }
+        let empty = match self.kind {
+            MetricKind::Histogram => SeriesValue::Histogram {
+                counts: vec![0; self.buckets.len() + 1],
+                sum: 0.0,
+                count: 0,
+                exemplars: vec![None; self.buckets.len() + 1],
+            },
+            _ => SeriesValue::Scalar(0.0),
+        };
+        Some((self.series.entry(labels).or_insert(empty), &self.buckets))
+    }
+}
+
+/// Declares a metric ahead of its first update. Metrics that are updated
+/// without being defined are created with default settings.
+#[napi(object)]
+pub struct MetricDescriptor {
+    pub name: String,
+    pub kind: MetricKind,
+    pub help: Option<String>,
+    /// Histogram upper bounds. At most 1024. Default: latency buckets from 5 ms to 10 s.
+    pub buckets: Option<Vec<f64>>,
+    /// Cardinality limit for this metric. Default: 50 series.
+    pub max_series: Option<u32>,
+}
+
+/// Datagram representing one labeled series of a metric.
+#[napi(object)]
+pub struct MetricSeries {
+    pub labels: HashMap<String, String>,
+    /// Counter or gauge value; the observation sum for histograms.
+    pub value: f64,
+    /// Histogram observation count.
+    pub count: Option<f64>,
+    /// Histogram cumulative counts, aligned with [`MetricFamily::buckets`] plus a final `+Inf` entry.
+    pub bucket_counts: Option<Vec<f64>>,
+}
+
+/// Datagram representing a metric and all of its series.
+#[napi(object)]
+pub struct MetricFamily {
+    pub name: String,
+    pub kind: MetricKind,
+    pub help: String,
+    pub buckets: Option<Vec<f64>>,
+    pub series: Vec<MetricSeries>,
+    /// Updates rejected by the cardinality limit since construction.
+    pub dropped_series: f64,
+}
+
+// --- END LABELED METRICS ---
+
+// --- COST ACCOUNTING ---
+
+const MAX_PRICED_MODELS: usize = 256;
+const MS_PER_DAY: f64 = 86_400_000.0;
+/// Counter fed by [`MetricsEngine::record_usage`].
+const COST_METRIC: &str = "ratchet_cost_usd_total";
+/// How far ahead of the local clock a caller-supplied timestamp may be.
+const MAX_TIMESTAMP_SKEW_MS: f64 = 5.0 * 60_000.0;
+
+/// Token prices for one model, in USD per million tokens.
+#[napi(object)]
+#[derive(Clone)]
+pub struct ModelPricing {
+    pub model: String,
+    pub provider: Option<String>,
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+    /// Price of prompt-cache reads. Default: the input price.
+    pub cached_per_million: Option<f64>,
+}
+
+/// Spend caps in USD. Days and months follow the UTC calendar.
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct CostBudget {
+    pub daily_usd: Option<f64>,
+    pub monthly_usd: Option<f64>,
+}
+
+/// Cost of one [`MetricsEngine::record_usage`] call.
+#[napi(object)]
+pub struct UsageCost {
+    pub cost_usd: f64,
+    /// The model has no registered price; its tokens were counted but not billed.
+    pub unpriced: bool,
+    /// This call pushed today's spend over the daily cap.
+    pub daily_budget_crossed: bool,
+    /// This call pushed this month's spend over the monthly cap.
+    pub monthly_budget_crossed: bool,
+}
+
+#[napi(object)]
+pub struct SpendEntry {
+    pub name: String,
+    pub usd: f64,
+}
+
+/// Accumulated spend and budget state.
+#[napi(object)]
+pub struct CostReport {
+    pub total_usd: f64,
+    pub daily_usd: f64,
+    pub monthly_usd: f64,
+    pub by_model: Vec<SpendEntry>,
+    pub by_provider: Vec<SpendEntry>,
+    /// Tokens recorded for models without a registered price.
+    pub unpriced_tokens: f64,
+    pub daily_budget_exceeded: bool,
+    pub monthly_budget_exceeded: bool,
+}
+
+/// Converts days since the Unix epoch to a running UTC month index (year * 12 + month).
+fn utc_month_index(days: i64) -> i64 {
+    // Howard Hinnant's civil_from_days.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    year * 12 + month - 1
+}
+
+/// Resolves a caller-supplied event time (default: now). Non-finite values
+/// are rejected and future ones clamped to the allowed skew, so a single bad
+/// timestamp cannot move the budget periods or the history windows far
+/// ahead of the clock.
+fn event_time_ms(timestamp_ms: Option<f64>) -> napi::Result<f64> {
+    let now = unix_time_ms();
+    match timestamp_ms {
+        None => Ok(now),
+        Some(ts) if ts.is_finite() => Ok(ts.min(now + MAX_TIMESTAMP_SKEW_MS)),
+        Some(ts) => Err(napi::Error::new(
+            napi::Status::InvalidArg,
+            format!("timestampMs must be a finite number, got {}", ts),
+        )),
+    }
+}
+
+fn validate_price(name: &str, value: f64) -> napi::Result<()> {
+    if value.is_finite() && value >= 0.0 {
+        Ok(())
+    } else {
+        Err(napi::Error::new(
+            napi::Status::InvalidArg,
+            format!("{} must be a non-negative number, got {}", name, value),
+        ))
+    }
+}
+
+/// Spend accumulated against the registered price table.
+struct CostLedger {
+    pricing: IndexMap<String, ModelPricing>,
+    budget: CostBudget,
+    spend_by_model: IndexMap<String, f64>,
+    total_usd: f64,
+    unpriced_tokens: f64,
+    day: i64,
+    daily_usd: f64,
+    month: i64,
+    monthly_usd: f64,
+}
+
+impl CostLedger {
+    fn new() -> Self {
+        CostLedger {
+            pricing: IndexMap::new(),
+            budget: CostBudget::default(),
+            spend_by_model: IndexMap::new(),
+            total_usd: 0.0,
+            unpriced_tokens: 0.0,
+            day: i64::MIN,
+            daily_usd: 0.0,
+            month: i64::MIN,
+            monthly_usd: 0.0,
+        }
+    }
+
+    /// Starts a new day/month when `now_ms` crosses a UTC boundary. Late
+    /// timestamps from an earlier period are billed to the current one.
+    fn roll(&mut self, now_ms: f64) {
+        let day = (now_ms / MS_PER_DAY).floor() as i64;
+        if day > self.day {
+            self.day = day;
+            self.daily_usd = 0.0;
+            let month = utc_month_index(day);
+            if month > self.month {
+                self.month = month;
+                self.monthly_usd = 0.0;
+            }
+        }
+    }
+
+    fn daily_exceeded(&self) -> bool {
+        self.budget.daily_usd.is_some_and(|cap| self.daily_usd >= cap)
+    }
+
+    fn monthly_exceeded(&self) -> bool {
+        self.budget.monthly_usd.is_some_and(|cap| self.monthly_usd >= cap)
+    }
+}
+
+// --- END COST ACCOUNTING ---
+
+// --- METRIC HISTORY ---
+
+/// Width and retention of each history resolution. Memory is fixed at
+/// 1440 + 336 + 90 buckets per engine.
+const HISTORY_MINUTE_SLOTS: usize = 1_440; // 24 hours
+const HISTORY_HOUR_SLOTS: usize = 336; // 14 days
+const HISTORY_DAY_SLOTS: usize = 90;
+
+#[derive(Clone, Copy)]
+struct HistoryBucket {
+    /// Bucket number since the epoch (`timestamp / width`); `i64::MIN` when unused.
+    index: i64,
+    tokens: f64,
+    cost_usd: f64,
+    latency_count: u64,
+    latency_sum: f64,
+    latency_min: f64,
+    latency_max: f64,
+}
+
+impl HistoryBucket {
+    const EMPTY: HistoryBucket = HistoryBucket {
+        index: i64::MIN,
+        tokens: 0.0,
+        cost_usd: 0.0,
+        latency_count: 0,
+        latency_sum: 0.0,
+        latency_min: f64::INFINITY,
+        latency_max: f64::NEG_INFINITY,
+    };
+}
+
+/// Fixed-size ring of time buckets; a slot is reused once its bucket falls
+/// out of the retention window.
+struct HistoryRing {
+    width_ms: f64,
+    slots: Vec<HistoryBucket>,
+    latest: i64,
+}
+
+impl HistoryRing {
+    fn new(width_ms: f64, slots: usize) -> Self {
+        HistoryRing { width_ms, slots: vec![HistoryBucket::EMPTY; slots], latest: i64::MIN }
+    }
+
+    fn retains(&self, index: i64) -> bool {
+        index > self.latest.saturating_sub(self.slots.len() as i64)
+    }
+
+    /// Returns the bucket covering `timestamp_ms`, or `None` when it is
+    /// older than the retention window.
+    fn bucket_mut(&mut self, timestamp_ms: f64) -> Option<&mut HistoryBucket> {
+        let index = (timestamp_ms / self.width_ms).floor() as i64;
+        self.latest = self.latest.max(index);
+        if !self.retains(index) {
+            return None;
+        }
+        let slot_count = self.slots.len() as i64;
+        let slot = &mut self.slots[index.rem_euclid(slot_count) as usize];
+        if slot.index != index {
+            *slot = HistoryBucket { index, ..HistoryBucket::EMPTY };
+        }
+        Some(slot)
+    }
+
+    fn range(&self, from_ms: f64, to_ms: f64) -> Vec<HistoryPoint> {
+        let mut buckets: Vec<&HistoryBucket> = self
+            .slots
+            .iter()
+            .filter(|b| b.index != i64::MIN && self.retains(b.index))
+            .filter(|b| {
+                let start = b.index as f64 * self.width_ms;
+                start + self.width_ms > from_ms && start <= to_ms
+            })
+            .collect();
+        buckets.sort_unstable_by_key(|b| b.index);
+        buckets
+            .into_iter()
+            .map(|b| {
+                let has_latency = b.latency_count > 0;
+                HistoryPoint {
+                    start_ms: b.index as f64 * self.width_ms,
+                    tokens: b.tokens,
+                    cost_usd: b.cost_usd,
+                    latency_count: b.latency_count as f64,
+                    avg_latency_ms: if has_latency { b.latency_sum / b.latency_count as f64 } else { 0.0 },
+                    min_latency_ms: if has_latency { b.latency_min } else { 0.0 },
+                    max_latency_ms: if has_latency { b.latency_max } else { 0.0 },
+                }
+            })
+            .collect()
+    }
+}
+
+/// Minute, hour and day rings, in that order.
+struct MetricHistory {
+    rings: [HistoryRing; 3],
+}
+
+impl MetricHistory {
+    fn new() -> Self {
+        MetricHistory {
+            rings: [
+                HistoryRing::new(60_000.0, HISTORY_MINUTE_SLOTS),
+                HistoryRing::new(3_600_000.0, HISTORY_HOUR_SLOTS),
+                HistoryRing::new(MS_PER_DAY, HISTORY_DAY_SLOTS),
+            ],
+        }
+    }
+
+    fn ring(&self, resolution: HistoryResolution) -> &HistoryRing {
+        match resolution {
+            HistoryResolution::Minute => &self.rings[0],
+            HistoryResolution::Hour => &self.rings[1],
+            HistoryResolution::Day => &self.rings[2],
+        }
+    }
+
+    fn record(&mut self, timestamp_ms: f64, apply: impl Fn(&mut HistoryBucket)) {
+        for ring in &mut self.rings {
+            if let Some(bucket) = ring.bucket_mut(timestamp_ms) {
+                apply(bucket);
+            }
+        }
+    }
+}
+
+/// Bucket width for [`MetricsEngine::history`].
+#[napi(string_enum = "snake_case")]
+#[derive(PartialEq, Eq, Debug)]
+pub enum HistoryResolution {
+    Minute,
+    Hour,
+    Day,
+}
+
+/// Aggregates for one history bucket.
+#[napi(object)]
+pub struct HistoryPoint {
+    /// Bucket start, in Unix milliseconds.
+    pub start_ms: f64,
+    pub tokens: f64,
+    pub cost_usd: f64,
+    pub latency_count: f64,
+    pub avg_latency_ms: f64,
+    pub min_latency_ms: f64,
+    pub max_latency_ms: f64,
+}
+
+// --- END METRIC HISTORY ---
+
+/// Datagram representing token consumption per model.
+#[napi(object)]
+pub struct ModelMetric {
+    pub model: String,
+    pub count: f64,
+}
+
+/// Comprehensive temporal and throughput summary of system performance.
+#[napi(object)]
+pub struct MetricsSummary {
+    pub total_tokens: f64,
+    pub model_breakdown: Vec<ModelMetric>,
+    pub avg_latency_ms: f64,
+    /// Number of latency samples the statistics below were computed over.
+    pub latency_samples: u32,
+    pub min_latency_ms: f64,
+    pub max_latency_ms: f64,
+    pub latency_std_dev_ms: f64,
+    pub p50_latency_ms: f64,
+    pub p90_latency_ms: f64,
+    pub p95_latency_ms: f64,
+    pub p99_latency_ms: f64,
+    /// Updates rejected by cardinality limits across all metrics.
+    pub dropped_series: f64,
+}
+
+const MAX_LATENCY_SAMPLES: usize = 100;
+const MAX_LATENCY_WINDOW: usize = 100_000; // Bounds the sort cost of summarize()
+const MAX_TRACKED_MODELS: usize = 50; // Protection against DoS
+
+/// Orchestrator for behavioral telemetry and performance auditing.
+#[napi]
+pub struct MetricsEngine {
+    metrics: IndexMap<String, Metric>,
+    /// Updates addressed to new metric names after `MAX_METRICS` was reached.
+    dropped_metrics: u64,
+    /// Tokens per model, kept outside the tokens metric so its cardinality
+    /// limit never hides usage that the cost ledger bills.
+    token_totals: IndexMap<String, f64>,
+    total_tokens: f64,
+    latency_samples: Vec<f64>,
+    latency_idx: usize, // Manual Ring Buffer index
+    latency_window: usize,
+    cost: CostLedger,
+    history: MetricHistory,
+    /// Start of the cumulative aggregation period, in Unix milliseconds.
+    start_time_ms: f64,
+}
+
+/// Linearly interpolated percentile of an ascending, non-empty slice.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+#[napi]
+impl MetricsEngine {
+    /// `latency_window` is the number of most recent latency samples the
+    /// summary statistics cover. Default: 100.
+    #[napi(constructor)]
+    pub fn new(latency_window: Option<u32>) -> Self {
+        let latency_window = latency_window
+            .map_or(MAX_LATENCY_SAMPLES, |w| w as usize)
+            .clamp(1, MAX_LATENCY_WINDOW);
+        let mut metrics = IndexMap::new();
+        metrics.insert(
+            TOKENS_METRIC.to_string(),
+            Metric::new(MetricKind::Counter, "Tokens consumed, by model.".to_string(), Vec::new(), DEFAULT_MAX_SERIES),
+        );
+        metrics.insert(
+            LATENCY_METRIC.to_string(),
+            Metric::new(MetricKind::Histogram, "Request latency in milliseconds.".to_string(), DEFAULT_LATENCY_BUCKETS.to_vec(), DEFAULT_MAX_SERIES),
+        );
+        metrics.insert(
+            COST_METRIC.to_string(),
+            Metric::new(MetricKind::Counter, "Spend in USD, by model and provider.".to_string(), Vec::new(), DEFAULT_MAX_SERIES),
+        );
+        MetricsEngine {
+            metrics,
+            dropped_metrics: 0,
+            token_totals: IndexMap::new(),
+            total_tokens: 0.0,
+            latency_samples: Vec::with_capacity(latency_window),
+            latency_idx: 0,
+            latency_window,
+            cost: CostLedger::new(),
+            history: MetricHistory::new(),
+            start_time_ms: unix_time_ms(),
+        }
+    }
+
+    /// Declares a metric with its kind, help text, buckets and cardinality
+    /// limit. Redefining an existing metric with a different kind fails;
+    /// lowering its limit evicts the oldest series.
+    #[napi]
+    pub fn define_metric(&mut self, descriptor: MetricDescriptor) -> napi::Result<()> {
+        let kind = descriptor.kind;
+        if !is_valid_metric_name(&descriptor.name) {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Invalid metric name: {}", descriptor.name),
+            ));
+        }
+        let mut buckets = descriptor.buckets.unwrap_or_else(|| DEFAULT_LATENCY_BUCKETS.to_vec());
+        buckets.retain(|b| b.is_finite());
+        buckets.sort_unstable_by(f64::total_cmp);
+        buckets.dedup();
+        if buckets.len() > MAX_SNAPSHOT_BUCKETS {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Too many histogram buckets: {} (max {})", buckets.len(), MAX_SNAPSHOT_BUCKETS),
+            ));
+        }
+        let max_series = descriptor.max_series.map_or(DEFAULT_MAX_SERIES, |m| m.max(1) as usize);
+
+        if let Some(existing) = self.metrics.get_mut(&descriptor.name) {
+            if existing.kind != kind {
+                return Err(napi::Error::new(
+                    napi::Status::InvalidArg,
+                    format!("Metric {} is already a {}", descriptor.name, existing.kind.as_str()),
+                ));
+            }
+            if let Some(help) = descriptor.help {
+                existing.help = help;
+            }
+            existing.max_series = max_series;
+            if existing.series.len() > max_series {
+                // Lowering the limit evicts the oldest series; they count as dropped.
+                let excess = existing.series.len() - max_series;
+                existing.series.drain(..excess);
+                existing.dropped_series += excess as u64;
+            }
+            if kind == MetricKind::Histogram && existing.buckets != buckets {
+                // Counts recorded against the old bounds cannot be rebucketed.
+                existing.buckets = buckets;
+                existing.series.clear();
+            }
+            return Ok(());
+        }
+        if self.metrics.len() >= MAX_METRICS {
+            return Err(napi::Error::new(
+                napi::Status::GenericFailure,
+                format!("Metric limit reached ({})", MAX_METRICS),
+            ));
+        }
+        self.metrics.insert(
+            descriptor.name,
+            Metric::new(kind, descriptor.help.unwrap_or_default(), buckets, max_series),
+        );
+        Ok(())
+    }
+
+    /// Adds `value` (default 1) to a counter. Returns `false` when the update
+    /// was dropped by the cardinality limit.
+    #[napi]
+    pub fn increment_counter(
+        &mut self,
+        name: String,
+        value: Option<f64>,
+        labels: Option<HashMap<String, String>>,
+    ) -> napi::Result<bool> {
+        let value = value.unwrap_or(1.0);
+        if !value.is_finite() || value < 0.0 {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Counter increment must be a non-negative number, got {}", value),
+            ));
+        }
+        self.update(&name, MetricKind::Counter, label_set(labels)?, |series, _| {
+            if let SeriesValue::Scalar(total) = series {
+                *total += value;
+            }
+        })
+    }
+
+    /// Sets a gauge to `value`.
+    #[napi]
+    pub fn set_gauge(
+        &mut self,
+        name: String,
+        value: f64,
+        labels: Option<HashMap<String, String>>,
+    ) -> napi::Result<bool> {
+        self.update(&name, MetricKind::Gauge, label_set(labels)?, |series, _| {
+            if let SeriesValue::Scalar(current) = series {
+                *current = value;
+            }
+        })
+    }
+
+    /// Adds `delta` (which may be negative) to a gauge.
+    #[napi]
+    pub fn add_gauge(
+        &mut self,
+        name: String,
+        delta: f64,
+        labels: Option<HashMap<String, String>>,
+    ) -> napi::Result<bool> {
+        self.update(&name, MetricKind::Gauge, label_set(labels)?, |series, _| {
+            if let SeriesValue::Scalar(current) = series {
+                *current += delta;
+            }
+        })
+    }
+
+    /// Records one histogram observation.
+    #[napi]
+    pub fn observe_histogram(
+        &mut self,
+        name: String,
+        value: f64,
+        labels: Option<HashMap<String, String>>,
+    ) -> napi::Result<bool> {
+        if !value.is_finite() {
+            return Ok(false);
+        }
+        self.update(&name, MetricKind::Histogram, label_set(labels)?, |series, buckets| observe(series, buckets, value, None))
+    }
+
+    /// Registers a token consumption event. `labels` may add dimensions such
+    /// as provider, channel, session or direction (input vs output).
+    #[napi]
+    pub fn record_tokens(
+        &mut self,
+        model: String,
+        count: u32,
+        labels: Option<HashMap<String, String>>,
+        timestamp_ms: Option<f64>,
+    ) -> napi::Result<bool> {
+        let now = event_time_ms(timestamp_ms)?;
+        let mut labels = labels.unwrap_or_default();
+        labels.insert("model".to_string(), model.clone());
+        let labels = label_set(Some(labels))?;
+        self.history.record(now, |b| b.tokens += count as f64);
+        self.total_tokens += count as f64;
+        if self.token_totals.len() < MAX_TRACKED_MODELS || self.token_totals.contains_key(&model) {
+            *self.token_totals.entry(model).or_insert(0.0) += count as f64;
+        }
+        self.update(TOKENS_METRIC, MetricKind::Counter, labels, |series, _| {
+            if let SeriesValue::Scalar(total) = series {
+                *total += count as f64;
+            }
+        })
+    }
+
+    /// Appends a latency sample to the internal rolling measurement window
+    /// and to the labeled latency histogram. `trace` links the sample to a
+    /// trace as the bucket's exemplar.
+    /// Used Ring Buffer logic to avoid O(N) shifts.
+    #[napi]
+    pub fn record_latency(
+        &mut self,
+        ms: f64,
+        labels: Option<HashMap<String, String>>,
+        timestamp_ms: Option<f64>,
+        trace: Option<TraceContext>,
+    ) -> napi::Result<bool> {
+        if !ms.is_finite() {
+            return Ok(false);
+        }
+        let now = event_time_ms(timestamp_ms)?;
+        let exemplar = Exemplar {
+            value: ms,
+            time_ms: now,
+            trace_id: trace.as_ref().map(|t| parse_trace_id(&t.trace_id, "traceId")).transpose()?,
+            span_id: trace.as_ref().and_then(|t| t.span_id.as_deref()).map(|id| parse_trace_id(id, "spanId")).transpose()?,
+        };
+        let labels = label_set(labels)?;
+        self.history.record(now, |b| {
+            b.latency_count += 1;
+            b.latency_sum += ms;
+            b.latency_min = b.latency_min.min(ms);
+            b.latency_max = b.latency_max.max(ms);
+        });
+        if self.latency_samples.len() < self.latency_window {
+            self.latency_samples.push(ms);
+        } else {
+            self.latency_samples[self.latency_idx] = ms;
+            self.latency_idx = (self.latency_idx + 1) % self.latency_window;
+        }
+        self.update(LATENCY_METRIC, MetricKind::Histogram, labels, |series, buckets| {
+            observe(series, buckets, ms, Some(exemplar))
+        })
+    }
+
+    /// Returns the non-empty `minute`, `hour` or `day` buckets overlapping
+    /// `[from_ms, to_ms]`, oldest first. Minutes are kept for 24 hours,
+    /// hours for 14 days and days for 90 days.
+    #[napi]
+    pub fn history(&self, resolution: HistoryResolution, from_ms: f64, to_ms: f64) -> Vec<HistoryPoint> {
+        self.history.ring(resolution).range(from_ms, to_ms)
+    }
+
+    /// Serializes the full engine state (metrics, latency window, prices,
+    /// spend and history) into a versioned, checksummed binary blob.
+    #[napi]
+    pub fn snapshot(&self) -> Buffer {
+        self.encode_snapshot().into()
+    }
+
+    /// Rebuilds an engine from a [`MetricsEngine::snapshot`] blob.
+    #[napi(factory)]
+    pub fn restore(snapshot: Buffer) -> napi::Result<Self> {
+        Self::decode_snapshot(&snapshot)
+    }
+
+    /// Encodes every metric as an OTLP `ExportMetricsServiceRequest`
+    /// protobuf, ready to POST to a collector's `/v1/metrics` endpoint.
+    /// Counters and histograms use cumulative temporality; latency buckets
+    /// carry their most recent sample as an exemplar.
+    #[napi]
+    pub fn export_otlp(&self, service_name: Option<String>, timestamp_ms: Option<f64>) -> Buffer {
+        self.encode_otlp(service_name.as_deref(), timestamp_ms.unwrap_or_else(unix_time_ms)).into()
+    }
+
+    /// Returns every metric with its labeled series.
+    #[napi]
+    pub fn collect(&self) -> Vec<MetricFamily> {
+        self.metrics
+            .iter()
+            .map(|(name, metric)| MetricFamily {
+                name: name.clone(),
+                kind: metric.kind,
+                help: metric.help.clone(),
+                buckets: (metric.kind == MetricKind::Histogram).then(|| metric.buckets.clone()),
+                series: metric
+                    .series
+                    .iter()
+                    .map(|(labels, value)| match value {
+                        SeriesValue::Scalar(v) => MetricSeries {
+                            labels: label_map(labels),
+                            value: *v,
+                            count: None,
+                            bucket_counts: None,
+                        },
+                        SeriesValue::Histogram { counts, sum, count, .. } => MetricSeries {
+                            labels: label_map(labels),
+                            value: *sum,
+                            count: Some(*count as f64),
+                            bucket_counts: Some(
+                                counts
+                                    .iter()
+                                    .scan(0u64, |acc, &c| {
+                                        *acc += c;
+                                        Some(*acc as f64)
+                                    })
+                                    .collect(),
+                            ),
+                        },
+                    })
+                    .collect(),
+                dropped_series: metric.dropped_series as f64,
+            })
+            .collect()
+    }
+
+    /// Renders every metric in the Prometheus text exposition format
+    /// (version 0.0.4), or in OpenMetrics 1.0 when `open_metrics` is set,
+    /// with HELP/TYPE lines and cumulative histogram buckets. Updates dropped
+    /// by cardinality limits are exported as `ratchet_dropped_series_total`.
+    #[napi]
+    pub fn render_prometheus(&self, open_metrics: Option<bool>) -> String {
+        let open_metrics = open_metrics.unwrap_or(false);
+        let mut out = String::new();
+        for (name, metric) in &self.metrics {
+            // OpenMetrics names the counter family without its `_total` suffix.
+            let (family, sample) = match metric.kind {
+                MetricKind::Counter if open_metrics => match name.strip_suffix("_total") {
+                    Some(base) => (base.to_string(), name.clone()),
+                    None => (name.clone(), format!("{}_total", name)),
+                },
+                _ => (name.clone(), name.clone()),
+            };
+            if !metric.help.is_empty() {
+                let help = metric.help.replace('\\', "\\\\").replace('\n', "\\n");
+                out.push_str(&format!("# HELP {} {}\n", family, help));
+            }
+            out.push_str(&format!("# TYPE {} {}\n", family, metric.kind.as_str()));
+            for (labels, value) in &metric.series {
+                match value {
+                    SeriesValue::Scalar(v) => write_sample(&mut out, &sample, labels, None, *v),
+                    SeriesValue::Histogram { counts, sum, count, .. } => {
+                        let bucket_name = format!("{}_bucket", name);
+                        let mut cumulative = 0;
+                        for (i, c) in counts.iter().enumerate() {
+                            cumulative += c;
+                            let le = metric.buckets.get(i).map_or("+Inf".to_string(), |&b| exposition_value(b));
+                            write_sample(&mut out, &bucket_name, labels, Some(("le", &le)), cumulative as f64);
+                        }
+                        write_sample(&mut out, &format!("{}_sum", name), labels, None, *sum);
+                        write_sample(&mut out, &format!("{}_count", name), labels, None, *count as f64);
+                    }
+                }
+            }
+        }
+
+        let dropped: Vec<(&String, u64)> = self
+            .metrics
+            .iter()
+            .filter(|(_, m)| m.dropped_series > 0)
+            .map(|(name, m)| (name, m.dropped_series))
+            .collect();
+        if !dropped.is_empty() || self.dropped_metrics > 0 {
+            let family = if open_metrics { "ratchet_dropped_series" } else { "ratchet_dropped_series_total" };
+            out.push_str(&format!("# HELP {} Updates rejected by metric cardinality limits.\n", family));
+            out.push_str(&format!("# TYPE {} counter\n", family));
+            for (name, count) in dropped {
+                let labels = vec![("metric".to_string(), name.clone())];
+                write_sample(&mut out, "ratchet_dropped_series_total", &labels, None, count as f64);
+            }
+            if self.dropped_metrics > 0 {
+                write_sample(&mut out, "ratchet_dropped_series_total", &Vec::new(), None, self.dropped_metrics as f64);
+            }
+        }
+        if open_metrics {
+            out.push_str("# EOF\n");
+        }
+        out
+    }
+
+    /// Registers or replaces per-model token prices.
+    #[napi]
+    pub fn set_pricing(&mut self, pricing: Vec<ModelPricing>) -> napi::Result<()> {
+        for price in &pricing {
+            validate_price("inputPerMillion", price.input_per_million)?;
+            validate_price("outputPerMillion", price.output_per_million)?;
+            if let Some(cached) = price.cached_per_million {
+                validate_price("cachedPerMillion", cached)?;
+            }
+        }
+        let new_models = pricing.iter().filter(|p| !self.cost.pricing.contains_key(&p.model)).count();
+        if self.cost.pricing.len() + new_models > MAX_PRICED_MODELS {
+            return Err(napi::Error::new(
+                napi::Status::GenericFailure,
+                format!("Price table limit reached ({})", MAX_PRICED_MODELS),
+            ));
+        }
+        for price in pricing {
+            self.cost.pricing.insert(price.model.clone(), price);
+        }
+        Ok(())
+    }
+
+    /// Sets the daily and monthly spend caps. Omitted caps are disabled.
+    #[napi]
+    pub fn set_budget(&mut self, budget: CostBudget) -> napi::Result<()> {
+        if let Some(cap) = budget.daily_usd {
+            validate_price("dailyUsd", cap)?;
+        }
+        if let Some(cap) = budget.monthly_usd {
+            validate_price("monthlyUsd", cap)?;
+        }
+        self.cost.budget = budget;
+        Ok(())
+    }
+
+    /// Records one model call: counts its tokens by direction (input,
+    /// output, cached) and bills them against the price table. Cached tokens
+    /// are billed separately from, not as part of, `input_tokens`.
+    #[napi]
+    pub fn record_usage(
+        &mut self,
+        model: String,
+        input_tokens: u32,
+        output_tokens: u32,
+        cached_tokens: Option<u32>,
+        timestamp_ms: Option<f64>,
+    ) -> napi::Result<UsageCost> {
+        let cached_tokens = cached_tokens.unwrap_or(0);
+        let now = event_time_ms(timestamp_ms)?;
+        for (direction, count) in [("input", input_tokens), ("output", output_tokens), ("cached", cached_tokens)] {
+            if count > 0 {
+                let labels = HashMap::from([("direction".to_string(), direction.to_string())]);
+                self.record_tokens(model.clone(), count, Some(labels), Some(now))?;
+            }
+        }
+
+        self.cost.roll(now);
+        let Some(price) = self.cost.pricing.get(&model) else {
+            self.cost.unpriced_tokens += (input_tokens as u64 + output_tokens as u64 + cached_tokens as u64) as f64;
+            return Ok(UsageCost { cost_usd: 0.0, unpriced: true, daily_budget_crossed: false, monthly_budget_crossed: false });
+        };
+        let cost_usd = (input_tokens as f64 * price.input_per_million
+            + output_tokens as f64 * price.output_per_million
+            + cached_tokens as f64 * price.cached_per_million.unwrap_or(price.input_per_million))
+            / 1_000_000.0;
+        let provider = price.provider.clone();
+
+        let (daily_before, monthly_before) = (self.cost.daily_exceeded(), self.cost.monthly_exceeded());
+        *self.cost.spend_by_model.entry(model.clone()).or_insert(0.0) += cost_usd;
+        self.cost.total_usd += cost_usd;
+        self.history.record(now, |b| b.cost_usd += cost_usd);
+        self.cost.daily_usd += cost_usd;
+        self.cost.monthly_usd += cost_usd;
+
+        let mut labels = HashMap::from([("model".to_string(), model)]);
+        if let Some(provider) = provider {
+            labels.insert("provider".to_string(), provider);
+        }
+        self.increment_counter(COST_METRIC.to_string(), Some(cost_usd), Some(labels))?;
+
+        Ok(UsageCost {
+            cost_usd,
+            unpriced: false,
+            daily_budget_crossed: !daily_before && self.cost.daily_exceeded(),
+            monthly_budget_crossed: !monthly_before && self.cost.monthly_exceeded(),
+        })
+    }
+
+    /// Reports spend per model, per provider and in total, with the budget
+    /// state as of `timestamp_ms` (default: now).
+    #[napi]
+    pub fn cost_report(&mut self, timestamp_ms: Option<f64>) -> napi::Result<CostReport> {
+        self.cost.roll(event_time_ms(timestamp_ms)?);
+        let mut by_provider: IndexMap<&str, f64> = IndexMap::new();
+        for (model, &usd) in &self.cost.spend_by_model {
+            let provider = self
+                .cost
+                .pricing
+                .get(model)
+                .and_then(|p| p.provider.as_deref())
+                .unwrap_or("unknown");
+            *by_provider.entry(provider).or_insert(0.0) += usd;
+        }
+        Ok(CostReport {
+            total_usd: self.cost.total_usd,
+            daily_usd: self.cost.daily_usd,
+            monthly_usd: self.cost.monthly_usd,
+            by_model: self
+                .cost
+                .spend_by_model
+                .iter()
+                .map(|(name, &usd)| SpendEntry { name: name.clone(), usd })
+                .collect(),
+            by_provider: by_provider
+                .into_iter()
+                .map(|(name, usd)| SpendEntry { name: name.to_string(), usd })
+                .collect(),
+            unpriced_tokens: self.cost.unpriced_tokens,
+            daily_budget_exceeded: self.cost.daily_exceeded(),
+            monthly_budget_exceeded: self.cost.monthly_exceeded(),
+        })
+    }
+
+    /// Synthesizes a point-in-time report of system-wide metrics.
+    #[napi]
+    pub fn summarize(&self) -> MetricsSummary {
+        let model_breakdown = self
+            .token_totals
+            .iter()
+            .map(|(k, &v)| ModelMetric { model: k.clone(), count: v })
+            .collect();
+
+        let mut sorted = self.latency_samples.clone();
+        sorted.sort_unstable_by(f64::total_cmp);
+        let n = sorted.len() as f64;
+        let (avg_latency, std_dev) = if sorted.is_empty() {
+            (0.0, 0.0)
+        } else {
+            let mean = sorted.iter().sum::<f64>() / n;
+            let variance = sorted.iter().map(|&v| (v - mean) * (v - mean)).sum::<f64>() / n;
+            (mean, variance.sqrt())
+        };
+        let pct = |p: f64| if sorted.is_empty() { 0.0 } else { percentile(&sorted, p) };
+        let dropped_series = self.dropped_metrics + self.metrics.values().map(|m| m.dropped_series).sum::<u64>();
+
+        MetricsSummary {
+            total_tokens: self.total_tokens,
+            model_breakdown,
+            avg_latency_ms: avg_latency,
+            latency_samples: sorted.len() as u32,
+            min_latency_ms: sorted.first().copied().unwrap_or(0.0),
+            max_latency_ms: sorted.last().copied().unwrap_or(0.0),
+            latency_std_dev_ms: std_dev,
+            p50_latency_ms: pct(50.0),
+            p90_latency_ms: pct(90.0),
+            p95_latency_ms: pct(95.0),
+            p99_latency_ms: pct(99.0),
+            dropped_series: dropped_series as f64,
+        }
+    }
+
+    /// Applies `apply` to the series addressed by `name`/`labels`, creating
+    /// the metric with default settings on first use.
+    fn update(
+        &mut self,
+        name: &str,
+        kind: MetricKind,
+        labels: LabelSet,
+        apply: impl FnOnce(&mut SeriesValue, &[f64]),
+    ) -> napi::Result<bool> {
+        if !self.metrics.contains_key(name) {
+            if !is_valid_metric_name(name) {
+                return Err(napi::Error::new(
+                    napi::Status::InvalidArg,
+                    format!("Invalid metric name: {}", name),
+                ));
+            }
+            if self.metrics.len() >= MAX_METRICS {
+                self.dropped_metrics += 1;
+                return Ok(false);
+            }
+            let buckets = if kind == MetricKind::Histogram { DEFAULT_LATENCY_BUCKETS.to_vec() } else { Vec::new() };
+            self.metrics.insert(name.to_string(), Metric::new(kind, String::new(), buckets, DEFAULT_MAX_SERIES));
+        }
+        let metric = self.metrics.get_mut(name).expect("metric inserted above");
+        if metric.kind != kind {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Metric {} is a {}, not a {}", name, metric.kind.as_str(), kind.as_str()),
+            ));
+        }
+        Ok(match metric.series_mut(labels) {
+            Some((series, buckets)) => {
+                apply(series, buckets);
+                true
+            }
+            None => false,
+        })
+    }
+}
+
+fn observe(series: &mut SeriesValue, buckets: &[f64], value: f64, exemplar: Option<Exemplar>) {
+    if let SeriesValue::Histogram { counts, sum, count, exemplars } = series {
+        let slot = buckets.partition_point(|&upper| upper < value);
+        counts[slot] += 1;
+        *sum += value;
+        *count += 1;
+        if exemplar.is_some() {
+            exemplars[slot] = exemplar;
+        }
+    }
+}
+
+impl Default for MetricsEngine {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+// --- METRICS SNAPSHOT ---
+
+/// Leading bytes of a [`MetricsEngine::snapshot`] blob.
+const SNAPSHOT_MAGIC: &[u8; 4] = b"RTMS";
+const SNAPSHOT_VERSION: u16 = 1;
+const MAX_SNAPSHOT_BUCKETS: usize = 1_024;
+
+/// FNV-1a, used as the snapshot integrity check.
+fn fnv1a64(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+/// Little-endian writer for the snapshot format.
+#[derive(Default)]
+struct SnapshotWriter {
+    buf: Vec<u8>,
+}
+
+impl SnapshotWriter {
+    fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    fn u32(&mut self, v: usize) {
+        self.buf.extend_from_slice(&(v as u32).to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn i64(&mut self, v: i64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn f64(&mut self, v: f64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn opt_f64(&mut self, v: Option<f64>) {
+        self.u8(v.is_some() as u8);
+        if let Some(v) = v {
+            self.f64(v);
+        }
+    }
+
+    fn str(&mut self, v: &str) {
+        self.u32(v.len());
+        self.buf.extend_from_slice(v.as_bytes());
+    }
+
+    fn opt_str(&mut self, v: Option<&str>) {
+        self.u8(v.is_some() as u8);
+        if let Some(v) = v {
+            self.str(v);
+        }
+    }
+}
+
+/// Bounds-checked reader for the snapshot format.
+struct SnapshotReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+fn corrupt_snapshot(reason: &str) -> napi::Error {
+    napi::Error::new(napi::Status::InvalidArg, format!("Corrupt metrics snapshot: {}", reason))
+}
+
+impl<'a> SnapshotReader<'a> {
+    fn take(&mut self, n: usize) -> napi::Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.data.len()).ok_or_else(|| corrupt_snapshot("truncated"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> napi::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().expect("take returns N bytes"))
+    }
+
+    fn u8(&mut self) -> napi::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> napi::Result<usize> {
+        Ok(u32::from_le_bytes(self.array()?) as usize)
+    }
+
+    /// Reads a length prefix, rejecting lengths above `max`.
+    fn len(&mut self, max: usize) -> napi::Result<usize> {
+        let n = self.u32()?;
+        if n > max {
+            return Err(corrupt_snapshot("length out of range"));
+        }
+        Ok(n)
+    }
+
+    fn u64(&mut self) -> napi::Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> napi::Result<i64> {
+        Ok(i64::from_le_bytes(self.array()?))
+    }
+
+    fn f64(&mut self) -> napi::Result<f64> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+
+    fn opt_f64(&mut self) -> napi::Result<Option<f64>> {
+        Ok(if self.u8()? != 0 { Some(self.f64()?) } else { None })
+    }
+
+    fn str(&mut self) -> napi::Result<String> {
+        let n = self.u32()?;
+        String::from_utf8(self.take(n)?.to_vec()).map_err(|_| corrupt_snapshot("invalid UTF-8"))
+    }
+
+    fn opt_str(&mut self) -> napi::Result<Option<String>> {
+        Ok(if self.u8()? != 0 { Some(self.str()?) } else { None })
+    }
+}
+
+fn write_metric(w: &mut SnapshotWriter, name: &str, metric: &Metric) {
+    w.str(name);
+    w.u8(match metric.kind {
+        MetricKind::Counter => 0,
+        MetricKind::Gauge => 1,
+        MetricKind::Histogram => 2,
+    });
+    w.str(&metric.help);
+    w.u32(metric.buckets.len());
+    metric.buckets.iter().for_each(|&b| w.f64(b));
+    w.u32(metric.max_series);
+    w.u64(metric.dropped_series);
+    w.u32(metric.series.len());
+    for (labels, value) in &metric.series {
+        w.u32(labels.len());
+        for (k, v) in labels {
+            w.str(k);
+            w.str(v);
+        }
+        match value {
+            SeriesValue::Scalar(v) => w.f64(*v),
+            SeriesValue::Histogram { counts, sum, count, .. } => {
+                counts.iter().for_each(|&c| w.u64(c));
+                w.f64(*sum);
+                w.u64(*count);
+            }
+        }
+    }
+}
+
+fn read_metric(r: &mut SnapshotReader) -> napi::Result<(String, Metric)> {
+    let name = r.str()?;
+    let kind = match r.u8()? {
+        0 => MetricKind::Counter,
+        1 => MetricKind::Gauge,
+        2 => MetricKind::Histogram,
+        _ => return Err(corrupt_snapshot("unknown metric kind")),
+    };
+    let help = r.str()?;
+    let buckets = (0..r.len(MAX_SNAPSHOT_BUCKETS)?).map(|_| r.f64()).collect::<napi::Result<Vec<_>>>()?;
+    let max_series = r.u32()?.max(1);
+    let mut metric = Metric::new(kind, help, buckets, max_series);
+    metric.dropped_series = r.u64()?;
+    for _ in 0..r.len(max_series)? {
+        let labels = (0..r.len(MAX_LABELS_PER_SERIES)?)
+            .map(|_| Ok((r.str()?, r.str()?)))
+            .collect::<napi::Result<LabelSet>>()?;
+        let value = match kind {
+            MetricKind::Histogram => SeriesValue::Histogram {
+                counts: (0..=metric.buckets.len()).map(|_| r.u64()).collect::<napi::Result<_>>()?,
+                sum: r.f64()?,
+                count: r.u64()?,
+                exemplars: vec![None; metric.buckets.len() + 1],
+            },
+            _ => SeriesValue::Scalar(r.f64()?),
+        };
+        metric.series.insert(labels, value);
+    }
+    Ok((name, metric))
+}
+
+impl MetricsEngine {
+    fn encode_snapshot(&self) -> Vec<u8> {
+        let mut w = SnapshotWriter::default();
+        w.buf.extend_from_slice(SNAPSHOT_MAGIC);
+        w.buf.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+
+        w.u32(self.metrics.len());
+        for (name, metric) in &self.metrics {
+            write_metric(&mut w, name, metric);
+        }
+        w.u64(self.dropped_metrics);
+        w.f64(self.start_time_ms);
+        w.u32(self.token_totals.len());
+        for (model, &tokens) in &self.token_totals {
+            w.str(model);
+            w.f64(tokens);
+        }
+        w.f64(self.total_tokens);
+
+        w.u32(self.latency_window);
+        w.u32(self.latency_idx);
+        w.u32(self.latency_samples.len());
+        self.latency_samples.iter().for_each(|&v| w.f64(v));
+
+        let cost = &self.cost;
+        w.u32(cost.pricing.len());
+        for price in cost.pricing.values() {
+            w.str(&price.model);
+            w.opt_str(price.provider.as_deref());
+            w.f64(price.input_per_million);
+            w.f64(price.output_per_million);
+            w.opt_f64(price.cached_per_million);
+        }
+        w.opt_f64(cost.budget.daily_usd);
+        w.opt_f64(cost.budget.monthly_usd);
+        w.u32(cost.spend_by_model.len());
+        for (model, &usd) in &cost.spend_by_model {
+            w.str(model);
+            w.f64(usd);
+        }
+        w.f64(cost.total_usd);
+        w.f64(cost.unpriced_tokens);
+        w.i64(cost.day);
+        w.f64(cost.daily_usd);
+        w.i64(cost.month);
+        w.f64(cost.monthly_usd);
+
+        for ring in &self.history.rings {
+            w.i64(ring.latest);
+            let used: Vec<&HistoryBucket> = ring.slots.iter().filter(|b| b.index != i64::MIN).collect();
+            w.u32(used.len());
+            for b in used {
+                w.i64(b.index);
+                w.f64(b.tokens);
+                w.f64(b.cost_usd);
+                w.u64(b.latency_count);
+                w.f64(b.latency_sum);
+                w.f64(b.latency_min);
+                w.f64(b.latency_max);
+            }
+        }
+
+        let checksum = fnv1a64(&w.buf);
+        w.u64(checksum);
+        w.buf
+    }
+
+    fn decode_snapshot(data: &[u8]) -> napi::Result<Self> {
+        if data.len() < SNAPSHOT_MAGIC.len() + 2 + 8 || &data[..4] != SNAPSHOT_MAGIC {
+            return Err(corrupt_snapshot("not a metrics snapshot"));
+        }
+        let (body, checksum) = data.split_at(data.len() - 8);
+        if fnv1a64(body) != u64::from_le_bytes(checksum.try_into().expect("8-byte checksum")) {
+            return Err(corrupt_snapshot("checksum mismatch"));
+        }
+        let version = u16::from_le_bytes([body[4], body[5]]);
+        if version == 0 || version > SNAPSHOT_VERSION {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Unsupported metrics snapshot version: {}", version),
+            ));
+        }
+        let mut r = SnapshotReader { data: body, pos: 6 };
+
+        let mut metrics = IndexMap::new();
+        for _ in 0..r.len(MAX_METRICS)? {
+            let (name, metric) = read_metric(&mut r)?;
+            metrics.insert(name, metric);
+        }
+        let dropped_metrics = r.u64()?;
+        let start_time_ms = r.f64()?;
+        let mut token_totals = IndexMap::new();
+        for _ in 0..r.len(MAX_TRACKED_MODELS)? {
+            let model = r.str()?;
+            token_totals.insert(model, r.f64()?);
+        }
+        let total_tokens = r.f64()?;
+
+        let latency_window = r.u32()?.clamp(1, MAX_LATENCY_WINDOW);
+        let latency_idx = r.u32()?;
+        let latency_samples = (0..r.len(latency_window)?).map(|_| r.f64()).collect::<napi::Result<Vec<_>>>()?;
+        if latency_idx >= latency_window {
+            return Err(corrupt_snapshot("latency index out of range"));
+        }
+
+        let mut cost = CostLedger::new();
+        for _ in 0..r.len(MAX_PRICED_MODELS)? {
+            let price = ModelPricing {
+                model: r.str()?,
+                provider: r.opt_str()?,
+                input_per_million: r.f64()?,
+                output_per_million: r.f64()?,
+                cached_per_million: r.opt_f64()?,
+            };
+            cost.pricing.insert(price.model.clone(), price);
+        }
+        cost.budget = CostBudget { daily_usd: r.opt_f64()?, monthly_usd: r.opt_f64()? };
+        for _ in 0..r.len(MAX_PRICED_MODELS)? {
+            let model = r.str()?;
+            cost.spend_by_model.insert(model, r.f64()?);
+        }
+        cost.total_usd = r.f64()?;
+        cost.unpriced_tokens = r.f64()?;
+        cost.day = r.i64()?;
+        cost.daily_usd = r.f64()?;
+        cost.month = r.i64()?;
+        cost.monthly_usd = r.f64()?;
+
+        let mut history = MetricHistory::new();
+        for ring in &mut history.rings {
+            ring.latest = r.i64()?;
+            let slot_count = ring.slots.len() as i64;
+            for _ in 0..r.len(ring.slots.len())? {
+                let bucket = HistoryBucket {
+                    index: r.i64()?,
+                    tokens: r.f64()?,
+                    cost_usd: r.f64()?,
+                    latency_count: r.u64()?,
+                    latency_sum: r.f64()?,
+                    latency_min: r.f64()?,
+                    latency_max: r.f64()?,
+                };
+                ring.slots[bucket.index.rem_euclid(slot_count) as usize] = bucket;
+            }
+        }
+
+        if r.pos != body.len() {
+            return Err(corrupt_snapshot("trailing bytes"));
+        }
+        Ok(MetricsEngine {
+            metrics,
+            dropped_metrics,
+            token_totals,
+            total_tokens,
+            latency_samples,
+            latency_idx,
+            latency_window,
+            cost,
+            history,
+            start_time_ms,
+        })
+    }
+}
+
+// --- END METRICS SNAPSHOT ---
+
+// --- OTLP EXPORT ---
+
+/// W3C trace context attached to a latency sample.
+#[napi(object)]
+pub struct TraceContext {
+    /// 32 hex characters.
+    pub trace_id: String,
+    /// 16 hex characters.
+    pub span_id: Option<String>,
+}
+
+/// Decodes a hex trace or span identifier of exactly `N` bytes.
+fn parse_trace_id<const N: usize>(hex: &str, field: &str) -> napi::Result<[u8; N]> {
+    let invalid = || {
+        napi::Error::new(
+            napi::Status::InvalidArg,
+            format!("{} must be {} hex characters", field, N * 2),
+        )
+    };
+    // `from_str_radix` alone would also accept a leading sign.
+    if hex.len() != N * 2 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let mut id = [0u8; N];
+    for (i, byte) in id.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(id)
+}
+
+/// OTLP `AggregationTemporality.AGGREGATION_TEMPORALITY_CUMULATIVE`.
+const OTLP_CUMULATIVE: u64 = 2;
+
+/// Minimal protobuf encoder covering the wire types OTLP metrics use.
+#[derive(Default)]
+struct ProtoWriter {
+    buf: Vec<u8>,
+}
+
+impl ProtoWriter {
+    fn varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.buf.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        self.buf.push(v as u8);
+    }
+
+    fn tag(&mut self, field: u32, wire_type: u8) {
+        self.varint(((field as u64) << 3) | wire_type as u64);
+    }
+
+    fn uint(&mut self, field: u32, v: u64) {
+        self.tag(field, 0);
+        self.varint(v);
+    }
+
+    fn fixed64(&mut self, field: u32, v: u64) {
+        self.tag(field, 1);
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn double(&mut self, field: u32, v: f64) {
+        self.tag(field, 1);
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn bytes(&mut self, field: u32, v: &[u8]) {
+        self.tag(field, 2);
+        self.varint(v.len() as u64);
+        self.buf.extend_from_slice(v);
+    }
+
+    fn string(&mut self, field: u32, v: &str) {
+        self.bytes(field, v.as_bytes());
+    }
+
+    /// Packed `repeated fixed64` / `repeated double`: both are 8-byte little-endian.
+    fn packed_fixed64(&mut self, field: u32, values: impl ExactSizeIterator<Item = [u8; 8]>) {
+        self.tag(field, 2);
+        self.varint(values.len() as u64 * 8);
+        values.for_each(|v| self.buf.extend_from_slice(&v));
+    }
+
+    fn message(&mut self, field: u32, build: impl FnOnce(&mut ProtoWriter)) {
+        let mut inner = ProtoWriter::default();
+        build(&mut inner);
+        self.bytes(field, &inner.buf);
+    }
+
+    /// `KeyValue { key = 1; AnyValue value = 2 { string_value = 1 } }`.
+    fn attribute(&mut self, field: u32, key: &str, value: &str) {
+        self.message(field, |kv| {
+            kv.string(1, key);
+            kv.message(2, |any| any.string(1, value));
+        });
+    }
+}
+
+fn unix_nanos(ms: f64) -> u64 {
+    (ms.max(0.0) * 1_000_000.0) as u64
+}
+
+/// OTLP unit for the built-in metrics; user-defined metrics carry none.
+fn otlp_unit(name: &str) -> &'static str {
+    match name {
+        TOKENS_METRIC => "{token}",
+        LATENCY_METRIC => "ms",
+        COST_METRIC => "USD",
+        _ => "",
+    }
+}
+
+impl MetricsEngine {
+    /// Encodes an `ExportMetricsServiceRequest` with one resource and one scope.
+    fn encode_otlp(&self, service_name: Option<&str>, now_ms: f64) -> Vec<u8> {
+        let start = unix_nanos(self.start_time_ms);
+        let now = unix_nanos(now_ms);
+        let mut request = ProtoWriter::default();
+        request.message(1, |resource_metrics| {
+            resource_metrics.message(1, |resource| {
+                resource.attribute(1, "service.name", service_name.unwrap_or("ratchet"));
+            });
+            resource_metrics.message(2, |scope_metrics| {
+                scope_metrics.message(1, |scope| {
+                    scope.string(1, "ratchet");
+                    scope.string(2, env!("CARGO_PKG_VERSION"));
+                });
+                for (name, metric) in self.metrics.iter().filter(|(_, m)| !m.series.is_empty()) {
+                    scope_metrics.message(2, |m| {
+                        m.string(1, name);
+                        m.string(2, &metric.help);
+                        m.string(3, otlp_unit(name));
+                        match metric.kind {
+                            MetricKind::Counter => m.message(7, |sum| {
+                                for (labels, value) in &metric.series {
+                                    sum.message(1, |point| encode_number_point(point, labels, value, start, now));
+                                }
+                                sum.uint(2, OTLP_CUMULATIVE);
+                                sum.uint(3, 1);
+                            }),
+                            MetricKind::Gauge => m.message(5, |gauge| {
+                                for (labels, value) in &metric.series {
+                                    gauge.message(1, |point| encode_number_point(point, labels, value, start, now));
+                                }
+                            }),
+                            MetricKind::Histogram => m.message(9, |histogram| {
+                                for (labels, value) in &metric.series {
+                                    histogram.message(1, |point| {
+                                        encode_histogram_point(point, labels, value, &metric.buckets, start, now)
+                                    });
+                                }
+                                histogram.uint(2, OTLP_CUMULATIVE);
+                            }),
+                        }
+                    });
+                }
+            });
+        });
+        request.buf
+    }
+}
+
+/// `NumberDataPoint { attributes = 7; start_time_unix_nano = 2; time_unix_nano = 3; as_double = 4 }`.
+fn encode_number_point(point: &mut ProtoWriter, labels: &LabelSet, value: &SeriesValue, start: u64, now: u64) {
+    for (k, v) in labels {
+        point.attribute(7, k, v);
+    }
+    point.fixed64(2, start);
+    point.fixed64(3, now);
+    if let SeriesValue::Scalar(v) = value {
+        point.double(4, *v);
+    }
+}
+
+/// `HistogramDataPoint { attributes = 9; start/time = 2/3; count = 4; sum = 5;
+/// bucket_counts = 6; explicit_bounds = 7; exemplars = 8 }`.
+fn encode_histogram_point(
+    point: &mut ProtoWriter,
+    labels: &LabelSet,
+    value: &SeriesValue,
+    bounds: &[f64],
+    start: u64,
+    now: u64,
+) {
+    let SeriesValue::Histogram { counts, sum, count, exemplars } = value else {
+        return;
+    };
+    for (k, v) in labels {
+        point.attribute(9, k, v);
+    }
+    point.fixed64(2, start);
+    point.fixed64(3, now);
+    point.fixed64(4, *count);
+    point.double(5, *sum);
+    point.packed_fixed64(6, counts.iter().map(|c| c.to_le_bytes()));
+    point.packed_fixed64(7, bounds.iter().map(|b| b.to_le_bytes()));
+    for exemplar in exemplars.iter().flatten() {
+        // Exemplar { time_unix_nano = 2; as_double = 3; span_id = 4; trace_id = 5 }
+        point.message(8, |e| {
+            e.fixed64(2, unix_nanos(exemplar.time_ms));
+            e.double(3, exemplar.value);
+            if let Some(span_id) = &exemplar.span_id {
+                e.bytes(4, span_id);
+            }
+            if let Some(trace_id) = &exemplar.trace_id {
+                e.bytes(5, trace_id);
+            }
+        });
+    }
+}
+
+// --- END OTLP EXPORT ---
+
+/// Expiry instant ordered with `f64::total_cmp`, for use as a `BTreeSet` key.
+#[derive(Clone, Copy)]
+struct Deadline(f64);
+
+impl PartialEq for Deadline {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.total_cmp(&other.0).is_eq()
+    }
+}
+
+impl Eq for Deadline {}
+
+impl PartialOrd for Deadline {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Deadline {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Optional behavior for [`RatchetDedupe`].
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct DedupeOptions {
+    /// Push a key's expiry forward each time it is seen again. Default: false.
+    pub sliding_expiry: Option<bool>,
+}
+
+struct DedupeEntry {
+    expires_at: f64,
+    ttl_ms: f64,
+}
+
+/// Temporal event deduplication utility with automatic cache pruning.
+///
+/// Keys expire by deadline rather than insertion order, so entries recorded
+/// with older explicit timestamps or shorter TTLs are pruned on time.
 #[napi]
-pub struct VadEngine {
-    is_talking: bool,
-    // Use Option<Instant> for monotonic time tracking.
-    // SystemTime is subject to NTP shifts and is unsafe for duration measurement.
-    silence_start: Option<Instant>, 
-    threshold: f64,
-    silence_timeout_ms: u64,
-    last_rms: f64,
+pub struct RatchetDedupe {
+    cache: HashMap<String, DedupeEntry>,
+    /// Expiry index: every cached key, soonest deadline first.
+    expiry: std::collections::BTreeSet<(Deadline, String)>,
+    ttl_ms: f64,
+    max_size: usize,
+    sliding_expiry: bool,
 }
 
 #[napi]
-impl VadEngine {
-    /// Initializes a new VAD processor with specific acoustic sensitivity.
+impl RatchetDedupe {
     #[napi(constructor)]
-    pub fn new(threshold: f64, silence_timeout_ms: u32) -> Self {
-        VadEngine {
-            is_talking: false,
-            silence_start: None,
-            threshold,
-            silence_timeout_ms: silence_timeout_ms as u64,
-            last_rms: 0.0,
+    pub fn new(ttl_ms: u32, max_size: u32, options: Option<DedupeOptions>) -> Self {
+        RatchetDedupe {
+            cache: HashMap::new(),
+            expiry: std::collections::BTreeSet::new(),
+            ttl_ms: ttl_ms as f64,
+            max_size: max_size as usize,
+            sliding_expiry: options.unwrap_or_default().sliding_expiry.unwrap_or(false),
         }
     }
 
-    /// Returns the RMS (Root Mean Square) energy of the last processed chunk.
-    #[napi(getter)]
-    pub fn last_rms(&self) -> f64 {
-        self.last_rms
-    }
-
-    /// Conducts acoustic analysis on a discrete segment of PCM audio.
+    /// Returns `true` the first time `key` is seen within its TTL. `ttl_ms`
+    /// overrides the default TTL for this key.
     #[napi]
-    pub fn process_chunk(&mut self, chunk: Vec<u8>) -> String {
-        if is_panic_mode() {
-            return "panic".to_string();
-        }
+    pub fn check(&mut self, key: String, timestamp_ms: Option<f64>, ttl_ms: Option<u32>) -> bool {
+        if is_panic_mode() { return false; }
+        if key.len() > MAX_KEY_LEN { return false; }
 
-        if chunk.is_empty() {
-            return "silent".to_string();
-        }
+        let now = timestamp_ms.unwrap_or_else(unix_time_ms);
 
-        // Optimization: Zero-cost abstraction for iteration
-        let mut sum_sq = 0.0;
-        let mut samples_count = 0;
+        self.prune(now);
 
-        for bytes in chunk.chunks_exact(2) {
-            let sample = i16::from_le_bytes([bytes[0], bytes[1]]) as f64;
-            sum_sq += sample * sample;
-            samples_count += 1;
+        if let Some(entry) = self.cache.get_mut(&key) {
+            if self.sliding_expiry {
+                if let Some(ttl) = ttl_ms {
+                    entry.ttl_ms = ttl as f64;
+                }
+                let expires_at = entry.expires_at.max(now + entry.ttl_ms);
+                self.expiry.remove(&(Deadline(entry.expires_at), key.clone()));
+                self.expiry.insert((Deadline(expires_at), key));
+                entry.expires_at = expires_at;
+            }
+            return false;
         }
 
-        if samples_count == 0 {
-            return "silent".to_string();
+        let ttl_ms = ttl_ms.map_or(self.ttl_ms, |ttl| ttl as f64);
+        let expires_at = now + ttl_ms;
+        self.expiry.insert((Deadline(expires_at), key.clone()));
+        self.cache.insert(key, DedupeEntry { expires_at, ttl_ms });
+
+        // Over capacity: drop the entry closest to expiring.
+        while self.cache.len() > self.max_size {
+            let Some((_, evicted)) = self.expiry.pop_first() else { break };
+            self.cache.remove(&evicted);
         }
-        
-        let rms = (sum_sq / samples_count as f64).sqrt();
-        self.last_rms = rms;
 
-        if rms > self.threshold {
-            self.silence_start = None;
-            if !self.is_talking {
-                self.is_talking = true;
-                return "speech_start".to_string();
-            }
-            "talking".to_string()
-        } else {
-            if self.is_talking {
-                match self.silence_start {
-                    None => {
-                        self.silence_start = Some(Instant::now());
-                    },
-                    Some(start) => {
-                        // Safe monotonic duration check
-                        if start.elapsed().as_millis() as u64 > self.silence_timeout_ms {
-                            self.is_talking = false;
-                            self.silence_start = None;
-                            return "speech_end".to_string();
-                        }
-                    }
-                }
-                "silencing".to_string()
+        true
+    }
+
+    #[napi]
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.expiry.clear();
+    }
+
+    #[napi]
+    pub fn size(&self) -> u32 {
+        self.cache.len() as u32
+    }
+
+    fn prune(&mut self, now: f64) {
+        while let Some((deadline, _)) = self.expiry.first() {
+            if now > deadline.0 {
+                let (_, key) = self.expiry.pop_first().expect("first entry exists");
+                self.cache.remove(&key);
             } else {
-                "silent".to_string()
+                break;
             }
         }
     }
 }
 
-/// [PT] Motor de Heurísticas de Backchannel (Escuta Ativa).
-/// Detecta janelas de oportunidade rítmicas para emitir reconhecimentos vocais curtos.
+// --- PROBABILISTIC DEDUPE ---
+
+/// Upper bound on total filter memory (all partitions).
+const MAX_BLOOM_BYTES: f64 = 256.0 * 1024.0 * 1024.0;
+
+/// Sizing for [`RatchetBloomDedupe`].
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct BloomDedupeOptions {
+    /// Distinct keys expected per TTL window. Default: 100000.
+    pub expected_items: Option<u32>,
+    /// Target probability that a new key is reported as a duplicate. Default: 0.001.
+    pub false_positive_rate: Option<f64>,
+    /// Time partitions covering the TTL; more partitions expire keys closer
+    /// to the TTL at the cost of memory. Default: 4, minimum 2.
+    pub partitions: Option<u32>,
+}
+
+/// Live state of a [`RatchetBloomDedupe`].
+#[napi(object)]
+pub struct BloomDedupeStats {
+    pub bits_per_partition: f64,
+    pub hash_functions: u32,
+    pub partitions: u32,
+    /// Fraction of bits set, averaged over partitions holding live keys.
+    pub fill_ratio: f64,
+    /// Distinct keys estimated from the fill of live partitions.
+    pub estimated_items: f64,
+    /// Current probability that a new key is reported as a duplicate.
+    pub estimated_false_positive_rate: f64,
+}
+
+/// splitmix64 finalizer: spreads every input bit across the output.
+fn mix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+struct BloomPartition {
+    /// Time slice this partition holds (`timestamp / span`); `i64::MIN` when unused.
+    epoch: i64,
+    bits: Vec<u64>,
+    set_bits: u64,
+}
+
+impl BloomPartition {
+    fn reset(&mut self, epoch: i64) {
+        self.epoch = epoch;
+        self.bits.iter_mut().for_each(|w| *w = 0);
+        self.set_bits = 0;
+    }
+
+    fn contains(&self, positions: &[u64]) -> bool {
+        positions.iter().all(|&p| self.bits[(p / 64) as usize] & (1 << (p % 64)) != 0)
+    }
+
+    fn insert(&mut self, positions: &[u64]) {
+        for &p in positions {
+            let word = &mut self.bits[(p / 64) as usize];
+            let mask = 1 << (p % 64);
+            if *word & mask == 0 {
+                *word |= mask;
+                self.set_bits += 1;
+            }
+        }
+    }
+}
+
+/// [PT] Deduplicação probabilística de alto volume.
+///
+/// Fixed-memory alternative to [`RatchetDedupe`] backed by a ring of Bloom
+/// filters, one per time slice of `ttl / (partitions - 1)`. A key is a
+/// duplicate if any live slice holds it, so keys are remembered for at least
+/// `ttl_ms` and at most `ttl_ms * partitions / (partitions - 1)`. Nothing is
+/// evicted early when traffic exceeds `expected_items`; the false-positive
+/// rate rises instead, and [`RatchetBloomDedupe::stats`] reports it.
 #[napi]
-pub struct BackchannelEngine {
-    energy_history: Vec<f64>,
-    history_limit: usize,
-    last_signal_time: Instant,
-    cooldown_ms: u64,
+pub struct RatchetBloomDedupe {
+    partitions: Vec<BloomPartition>,
+    span_ms: f64,
+    bits: u64,
+    hashes: u32,
+    hasher: std::collections::hash_map::RandomState,
+    latest_epoch: i64,
 }
 
 #[napi]
-impl BackchannelEngine {
+impl RatchetBloomDedupe {
     #[napi(constructor)]
-    pub fn new(history_limit: u32, cooldown_ms: u32) -> Self {
-        BackchannelEngine {
-            energy_history: Vec::with_capacity(history_limit as usize),
-            history_limit: history_limit as usize,
-            last_signal_time: Instant::now() - Duration::from_millis(cooldown_ms as u64),
-            cooldown_ms: cooldown_ms as u64,
+    pub fn new(ttl_ms: u32, options: Option<BloomDedupeOptions>) -> napi::Result<Self> {
+        let options = options.unwrap_or_default();
+        let partitions = options.partitions.unwrap_or(4).clamp(2, 64) as usize;
+        let expected = options.expected_items.unwrap_or(100_000).max(1) as f64;
+        let fp_rate = options.false_positive_rate.unwrap_or(0.001);
+        if !(fp_rate > 0.0 && fp_rate < 1.0) {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("falsePositiveRate must be between 0 and 1, got {}", fp_rate),
+            ));
+        }
+
+        // Every live partition is queried, so each gets an even share of the
+        // error budget. Each is sized for the full expected load so a burst
+        // landing in one time slice stays within the target rate.
+        let per_partition_items = expected;
+        let per_partition_fp = fp_rate / partitions as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-per_partition_items * per_partition_fp.ln() / (ln2 * ln2)).ceil().max(64.0);
+        if bits * partitions as f64 / 8.0 > MAX_BLOOM_BYTES {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                "Bloom filter would exceed 256 MiB; lower expectedItems or raise falsePositiveRate",
+            ));
         }
+        let words = (bits as usize).div_ceil(64);
+        let bits = words as u64 * 64;
+        let hashes = ((bits as f64 / per_partition_items) * ln2).round().clamp(1.0, 32.0) as u32;
+
+        Ok(RatchetBloomDedupe {
+            partitions: (0..partitions)
+                .map(|_| BloomPartition { epoch: i64::MIN, bits: vec![0; words], set_bits: 0 })
+                .collect(),
+            span_ms: (ttl_ms.max(1) as f64) / (partitions - 1) as f64,
+            bits,
+            hashes,
+            hasher: std::collections::hash_map::RandomState::new(),
+            latest_epoch: i64::MIN,
+        })
     }
 
+    /// Returns `true` the first time `key` is seen within the TTL. May
+    /// return `false` for a new key with the reported false-positive rate.
     #[napi]
-    pub fn process_energy(&mut self, rms: f64) -> bool {
+    pub fn check(&mut self, key: String, timestamp_ms: Option<f64>) -> bool {
         if is_panic_mode() { return false; }
+        if key.len() > MAX_KEY_LEN { return false; }
 
-        // Manter histórico de energia
-        if self.energy_history.len() >= self.history_limit {
-            self.energy_history.remove(0);
-        }
-        self.energy_history.push(rms);
+        let now = timestamp_ms.unwrap_or_else(unix_time_ms);
+        // Late timestamps are filed under the newest slice.
+        let epoch = ((now / self.span_ms).floor() as i64).max(self.latest_epoch);
+        self.latest_epoch = epoch;
 
-        // Só disparar se o cooldown expirou
-        if self.last_signal_time.elapsed().as_millis() < self.cooldown_ms as u128 {
+        let positions = self.positions(&key);
+        let live = self.partitions.len() as i64;
+        if self
+            .partitions
+            .iter()
+            .any(|p| p.epoch != i64::MIN && epoch - p.epoch < live && p.contains(&positions))
+        {
             return false;
         }
 
-        // Heurística: Queda súbita de energia após um período de atividade (indica pausa natural)
-        if self.energy_history.len() == self.history_limit {
-            let avg_energy: f64 = self.energy_history.iter().sum::<f64>() / self.history_limit as f64;
-            
-            // Se a energia atual está 70% abaixo da média do histórico recente, 
-            // e a média era significativamente alta (ex: voz ativa)
-            if rms < avg_energy * 0.3 && avg_energy > 500.0 {
-                self.last_signal_time = Instant::now();
-                return true;
+        let slot = epoch.rem_euclid(live) as usize;
+        let partition = &mut self.partitions[slot];
+        if partition.epoch != epoch {
+            partition.reset(epoch);
+        }
+        partition.insert(&positions);
+        true
+    }
+
+    #[napi]
+    pub fn clear(&mut self) {
+        self.partitions.iter_mut().for_each(|p| p.reset(i64::MIN));
+        self.latest_epoch = i64::MIN;
+    }
+
+    #[napi]
+    pub fn stats(&self) -> BloomDedupeStats {
+        let live = self.partitions.len() as i64;
+        let m = self.bits as f64;
+        let k = self.hashes as f64;
+        let (mut fill_sum, mut used, mut items, mut miss_all) = (0.0, 0, 0.0, 1.0);
+        for p in &self.partitions {
+            if p.epoch == i64::MIN || self.latest_epoch - p.epoch >= live {
+                continue;
             }
+            let fill = p.set_bits as f64 / m;
+            fill_sum += fill;
+            used += 1;
+            // Swamidass-Baldi cardinality estimate.
+            items += if fill < 1.0 { -m / k * (1.0 - fill).ln() } else { f64::INFINITY };
+            miss_all *= 1.0 - fill.powf(k);
+        }
+        BloomDedupeStats {
+            bits_per_partition: m,
+            hash_functions: self.hashes,
+            partitions: live as u32,
+            fill_ratio: if used > 0 { fill_sum / used as f64 } else { 0.0 },
+            estimated_items: items,
+            estimated_false_positive_rate: 1.0 - miss_all,
         }
+    }
 
-        false
+    /// Bit positions for `key` by Kirsch-Mitzenmacher double hashing.
+    fn positions(&self, key: &str) -> Vec<u64> {
+        use std::hash::BuildHasher;
+        let h1 = self.hasher.hash_one(key);
+        let h2 = mix64(h1) | 1;
+        (0..self.hashes as u64)
+            .map(|i| h1.wrapping_add(i.wrapping_mul(h2)) % self.bits)
+            .collect()
     }
 }
 
-/// Datagram representing token consumption per model.
+// --- END PROBABILISTIC DEDUPE ---
+
+// --- NEAR-DUPLICATE DETECTION ---
+
+/// Character n-gram length fed into the SimHash.
+const SIMHASH_SHINGLE: usize = 4;
+
+/// Words a message needs before it is fingerprinted. Shorter replies such
+/// as "ok" or "yes" collide with each other too easily.
+const NEAR_DEDUPE_MIN_WORDS: u32 = 4;
+
+/// Tuning for [`RatchetNearDedupe`].
 #[napi(object)]
-pub struct ModelMetric {
-    pub model: String,
-    pub count: f64,
+#[derive(Clone, Default)]
+pub struct NearDedupeOptions {
+    /// Largest Hamming distance between 64-bit fingerprints that still
+    /// counts as a duplicate. Unrelated texts land near 32. Default: 6, max 63.
+    pub max_distance: Option<u32>,
+    /// Fingerprints retained across all scopes. Default: 10000.
+    pub max_size: Option<u32>,
+    /// Messages with fewer normalized words are skipped. Default: 4.
+    pub min_words: Option<u32>,
 }
 
-/// Comprehensive temporal and throughput summary of system performance.
+/// Outcome of [`RatchetNearDedupe::check`].
 #[napi(object)]
-pub struct MetricsSummary {
-    pub total_tokens: f64,
-    pub model_breakdown: Vec<ModelMetric>,
-    pub avg_latency_ms: f64,
+pub struct NearDuplicateResult {
+    pub duplicate: bool,
+    /// Hamming distance to the closest fingerprint within the TTL that
+    /// shares a band with this one (64 when none does).
+    pub distance: u32,
+    /// `1 - distance / 64`.
+    pub similarity: f64,
+    /// SimHash of the message, as 16 hex characters. Empty when skipped.
+    pub fingerprint: String,
+    /// The message was too short to compare and was not recorded.
+    pub skipped: bool,
+    /// Recording this message evicted the oldest live fingerprint because
+    /// the detector was full. Raise `maxSize` if this happens routinely.
+    pub evicted: bool,
 }
 
-const MAX_LATENCY_SAMPLES: usize = 100;
-const MAX_TRACKED_MODELS: usize = 50; // Protection against DoS
+/// Folds `text` for similarity comparison: shared normalization, lowercase,
+/// and punctuation/whitespace runs collapsed to single spaces.
+fn similarity_text(text: &str) -> String {
+    let lowered = normalize_text(text).to_lowercase();
+    lowered
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-/// Orchestrator for behavioral telemetry and performance auditing.
+/// 64-bit SimHash over character shingles; `None` for text with no content.
+fn simhash(text: &str) -> Option<u64> {
+    let chars: Vec<char> = similarity_text(text).chars().collect();
+    if chars.is_empty() {
+        return None;
+    }
+    let mut weights = [0i32; 64];
+    let mut shingle = String::new();
+    for window in chars.windows(SIMHASH_SHINGLE.min(chars.len())) {
+        shingle.clear();
+        shingle.extend(window);
+        let hash = mix64(fnv1a64(shingle.as_bytes()));
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if hash >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+    Some(weights.iter().enumerate().fold(0u64, |acc, (bit, &w)| if w > 0 { acc | 1 << bit } else { acc }))
+}
+
+struct NearEntry {
+    fingerprint: u64,
+    scope: u64,
+    seen_at: f64,
+}
+
+/// [PT] Detector de mensagens quase duplicadas.
+///
+/// Flags messages whose normalized text is near-identical to one seen within
+/// the TTL, catching re-deliveries that differ only in envelope details,
+/// casing, punctuation or Unicode presentation.
+///
+/// Fingerprints are split into `maxDistance + 1` bands; two fingerprints
+/// within the distance agree on at least one band, so only entries sharing
+/// a band are compared.
 #[napi]
-pub struct MetricsEngine {
-    token_counts: IndexMap<String, f64>,
-    latency_samples: Vec<f64>,
-    latency_idx: usize, // Manual Ring Buffer index
+pub struct RatchetNearDedupe {
+    /// Entries by insertion id, oldest first.
+    entries: std::collections::BTreeMap<u64, NearEntry>,
+    /// `(scope, band, band bits)` to the ids of entries with those bits.
+    bands: HashMap<(u64, u8, u64), Vec<u64>>,
+    next_id: u64,
+    ttl_ms: f64,
+    max_distance: u32,
+    max_size: usize,
+    min_words: usize,
 }
 
 #[napi]
-impl MetricsEngine {
+impl RatchetNearDedupe {
     #[napi(constructor)]
-    pub fn new() -> Self {
-        MetricsEngine {
-            token_counts: IndexMap::new(),
-            latency_samples: Vec::with_capacity(MAX_LATENCY_SAMPLES),
-            latency_idx: 0,
+    pub fn new(ttl_ms: u32, options: Option<NearDedupeOptions>) -> Self {
+        let options = options.unwrap_or_default();
+        RatchetNearDedupe {
+            entries: std::collections::BTreeMap::new(),
+            bands: HashMap::new(),
+            next_id: 0,
+            ttl_ms: ttl_ms as f64,
+            max_distance: options.max_distance.unwrap_or(6).min(63),
+            max_size: options.max_size.unwrap_or(10_000).max(1) as usize,
+            min_words: options.min_words.unwrap_or(NEAR_DEDUPE_MIN_WORDS) as usize,
         }
     }
 
-    /// Registers a token consumption event.
+    /// Compares `text` against fingerprints seen within the TTL in the same
+    /// `scope` (e.g. a channel or conversation id) and records it when it is
+    /// not a duplicate.
     #[napi]
-    pub fn record_tokens(&mut self, model: String, count: u32) {
-        // DoS Protection: Prevent unlimited map growth
-        if self.token_counts.len() >= MAX_TRACKED_MODELS && !self.token_counts.contains_key(&model) {
-             // Silently drop new keys if full to preserve stability
-             return;
+    pub fn check(&mut self, text: String, timestamp_ms: Option<f64>, scope: Option<String>) -> NearDuplicateResult {
+        let not_compared = |duplicate| NearDuplicateResult {
+            duplicate,
+            distance: 64,
+            similarity: 0.0,
+            fingerprint: String::new(),
+            skipped: true,
+            evicted: false,
+        };
+        // Like `RatchetDedupe`, panic mode suppresses every message.
+        if is_panic_mode() {
+            return not_compared(true);
+        }
+        let folded = similarity_text(&text);
+        if folded.is_empty() || folded.split(' ').count() < self.min_words.max(1) {
+            return not_compared(false);
+        }
+        let fingerprint = simhash(&text).expect("non-empty text hashes");
+        let scope = fnv1a64(scope.unwrap_or_default().as_bytes());
+        let now = timestamp_ms.unwrap_or_else(unix_time_ms);
+
+        while let Some((&id, entry)) = self.entries.first_key_value() {
+            if now - entry.seen_at > self.ttl_ms {
+                self.remove(id);
+            } else {
+                break;
+            }
+        }
+
+        // Entries recorded with later explicit timestamps may sit behind
+        // expired ones, so the TTL is checked per entry as well.
+        let mut distance = 64;
+        for key in self.band_keys(scope, fingerprint) {
+            for id in self.bands.get(&key).into_iter().flatten() {
+                let entry = &self.entries[id];
+                if now - entry.seen_at <= self.ttl_ms {
+                    distance = distance.min((fingerprint ^ entry.fingerprint).count_ones());
+                }
+            }
+        }
+        let duplicate = distance <= self.max_distance;
+        let mut evicted = false;
+        if !duplicate {
+            if self.entries.len() >= self.max_size {
+                let oldest = *self.entries.keys().next().expect("a full detector has entries");
+                self.remove(oldest);
+                evicted = true;
+            }
+            let id = self.next_id;
+            self.next_id += 1;
+            for key in self.band_keys(scope, fingerprint) {
+                self.bands.entry(key).or_default().push(id);
+            }
+            self.entries.insert(id, NearEntry { fingerprint, scope, seen_at: now });
+        }
+
+        NearDuplicateResult {
+            duplicate,
+            distance,
+            similarity: 1.0 - distance as f64 / 64.0,
+            fingerprint: format!("{:016x}", fingerprint),
+            skipped: false,
+            evicted,
         }
-        let entry = self.token_counts.entry(model).or_insert(0.0);
-        *entry += count as f64;
     }
 
-    /// Appends a latency sample to the internal rolling measurement window.
-    /// Used Ring Buffer logic to avoid O(N) shifts.
     #[napi]
-    pub fn record_latency(&mut self, ms: f64) {
-        if self.latency_samples.len() < MAX_LATENCY_SAMPLES {
-            self.latency_samples.push(ms);
-        } else {
-            self.latency_samples[self.latency_idx] = ms;
-            self.latency_idx = (self.latency_idx + 1) % MAX_LATENCY_SAMPLES;
-        }
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.bands.clear();
     }
 
-    /// Synthesizes a point-in-time report of system-wide metrics.
     #[napi]
-    pub fn summarize(&self) -> MetricsSummary {
-        let total_tokens = self.token_counts.values().sum();
-        let model_breakdown = self.token_counts
-            .iter()
-            .map(|(k, &v)| ModelMetric { model: k.clone(), count: v })
-            .collect();
-        
-        let avg_latency = if self.latency_samples.is_empty() {
-            0.0
-        } else {
-            let sum: f64 = self.latency_samples.iter().sum();
-            sum / self.latency_samples.len() as f64
-        };
+    pub fn size(&self) -> u32 {
+        self.entries.len() as u32
+    }
 
-        MetricsSummary {
-            total_tokens,
-            model_breakdown,
-            avg_latency_ms: avg_latency,
+    fn band_keys(&self, scope: u64, fingerprint: u64) -> impl Iterator<Item = (u64, u8, u64)> {
+        let count = self.max_distance + 1;
+        (0..count).map(move |band| {
+            let (start, end) = (band * 64 / count, (band + 1) * 64 / count);
+            let mask = if end - start == 64 { u64::MAX } else { (1u64 << (end - start)) - 1 };
+            (scope, band as u8, fingerprint >> start & mask)
+        })
+    }
+
+    fn remove(&mut self, id: u64) {
+        let Some(entry) = self.entries.remove(&id) else { return };
+        for key in self.band_keys(entry.scope, entry.fingerprint).collect::<Vec<_>>() {
+            if let Some(ids) = self.bands.get_mut(&key) {
+                ids.retain(|&other| other != id);
+                if ids.is_empty() {
+                    self.bands.remove(&key);
+                }
+            }
         }
     }
 }
 
-/// Temporal event deduplication utility with automatic cache pruning.
+// --- END NEAR-DUPLICATE DETECTION ---
+
+// --- RATE LIMITING ---
+
+const DEFAULT_RATE_LIMIT_KEYS: u32 = 10_000;
+/// Longest key accepted, matching [`RatchetDedupe::check`].
+const MAX_KEY_LEN: usize = 1024;
+
+/// Metering strategy for [`RatchetRateLimiter`].
+#[napi(string_enum = "snake_case")]
+#[derive(PartialEq, Debug)]
+pub enum RateLimitAlgorithm {
+    TokenBucket,
+    SlidingWindow,
+}
+
+/// Construction parameters for [`RatchetRateLimiter`].
+#[napi(object)]
+pub struct RateLimitOptions {
+    /// Default: `token_bucket`.
+    pub algorithm: Option<RateLimitAlgorithm>,
+    /// Requests allowed per window; also the token bucket's burst capacity.
+    pub limit: u32,
+    /// Window length. The token bucket refills `limit` tokens over this span.
+    pub window_ms: u32,
+    /// Keys tracked before the least recently checked one is evicted. Default: 10000.
+    pub max_keys: Option<u32>,
+}
+
+/// Outcome of [`RatchetRateLimiter::check`].
+#[napi(object)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    /// Requests still available to this key right now.
+    pub remaining: f64,
+    /// Wait before a request of the same cost would be allowed; 0 when allowed.
+    pub retry_after_ms: f64,
+}
+
+enum RateLimitState {
+    Bucket { tokens: f64, updated: f64 },
+    /// `(timestamp, cost)` of each admitted request inside the window, plus their total.
+    Window { log: std::collections::VecDeque<(f64, u32)>, used: u64 },
+}
+
+struct RateLimitEntry {
+    state: RateLimitState,
+    /// Position in [`RatchetRateLimiter::recency`].
+    last_used: u64,
+}
+
+/// [PT] Limitador de taxa nativo por chave.
+///
+/// Token bucket or sliding-window log limiter keyed by string. Once
+/// `max_keys` is reached the least recently checked key is evicted, so
+/// active keys keep their state while a flood of new keys churns.
 #[napi]
-pub struct RatchetDedupe {
-    cache: IndexMap<String, f64>,
-    ttl_ms: f64,
-    max_size: usize,
+pub struct RatchetRateLimiter {
+    algorithm: RateLimitAlgorithm,
+    limit: u32,
+    window_ms: f64,
+    max_keys: usize,
+    keys: HashMap<String, RateLimitEntry>,
+    /// Eviction index: every tracked key, least recently checked first.
+    recency: std::collections::BTreeMap<u64, String>,
+    /// Monotonic check counter that orders `recency`.
+    clock: u64,
 }
 
 #[napi]
-impl RatchetDedupe {
+impl RatchetRateLimiter {
     #[napi(constructor)]
-    pub fn new(ttl_ms: u32, max_size: u32) -> Self {
-        RatchetDedupe {
-            cache: IndexMap::new(),
-            ttl_ms: ttl_ms as f64,
-            max_size: max_size as usize,
+    pub fn new(options: RateLimitOptions) -> napi::Result<Self> {
+        let algorithm = options.algorithm.unwrap_or(RateLimitAlgorithm::TokenBucket);
+        if options.limit == 0 || options.window_ms == 0 {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                "Rate limit and window must be positive",
+            ));
         }
+        Ok(RatchetRateLimiter {
+            algorithm,
+            limit: options.limit,
+            window_ms: options.window_ms as f64,
+            max_keys: options.max_keys.unwrap_or(DEFAULT_RATE_LIMIT_KEYS).max(1) as usize,
+            keys: HashMap::new(),
+            recency: std::collections::BTreeMap::new(),
+            clock: 0,
+        })
     }
 
+    /// Consumes `cost` (default 1, at most `limit`) from `key`'s allowance
+    /// if available.
     #[napi]
-    pub fn check(&mut self, key: String, timestamp_ms: Option<f64>) -> bool {
-        if is_panic_mode() { return false; }
-        if key.len() > 1024 { return false; }
-
-        let now = timestamp_ms.unwrap_or_else(|| {
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_millis() as f64
-        });
-
-        self.prune(now);
+    pub fn check(&mut self, key: String, cost: Option<u32>, timestamp_ms: Option<f64>) -> napi::Result<RateLimitDecision> {
+        let cost = cost.unwrap_or(1);
+        if cost == 0 {
+            return Err(napi::Error::new(napi::Status::InvalidArg, "Cost must be positive"));
+        }
+        if cost > self.limit {
+            return Err(napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Cost {} exceeds the rate limit of {}", cost, self.limit),
+            ));
+        }
+        if is_panic_mode() || key.len() > MAX_KEY_LEN {
+            return Ok(RateLimitDecision { allowed: false, remaining: 0.0, retry_after_ms: self.window_ms });
+        }
+        let now = timestamp_ms.unwrap_or_else(unix_time_ms);
 
-        if self.cache.contains_key(&key) {
-            return false;
+        self.clock += 1;
+        if let Some(entry) = self.keys.get_mut(&key) {
+            self.recency.remove(&entry.last_used);
+            entry.last_used = self.clock;
+        } else {
+            if self.keys.len() >= self.max_keys {
+                if let Some((_, evicted)) = self.recency.pop_first() {
+                    self.keys.remove(&evicted);
+                }
+            }
+            let state = match self.algorithm {
+                RateLimitAlgorithm::TokenBucket => RateLimitState::Bucket { tokens: self.limit as f64, updated: now },
+                RateLimitAlgorithm::SlidingWindow => RateLimitState::Window { log: std::collections::VecDeque::new(), used: 0 },
+            };
+            self.keys.insert(key.clone(), RateLimitEntry { state, last_used: self.clock });
         }
+        self.recency.insert(self.clock, key.clone());
+        let (limit, window_ms) = (self.limit as f64, self.window_ms);
+        let state = &mut self.keys.get_mut(&key).expect("key tracked above").state;
 
-        self.cache.insert(key, now);
+        Ok(match state {
+            RateLimitState::Bucket { tokens, updated } => {
+                let rate = limit / window_ms;
+                // Late timestamps neither refill nor rewind the bucket.
+                *tokens = (*tokens + (now - *updated).max(0.0) * rate).min(limit);
+                *updated = updated.max(now);
+                if *tokens >= cost as f64 {
+                    *tokens -= cost as f64;
+                    RateLimitDecision { allowed: true, remaining: tokens.floor(), retry_after_ms: 0.0 }
+                } else {
+                    RateLimitDecision {
+                        allowed: false,
+                        remaining: tokens.floor(),
+                        retry_after_ms: ((cost as f64 - *tokens) / rate).ceil(),
+                    }
+                }
+            }
+            RateLimitState::Window { log, used } => {
+                // Keep the log ordered: late timestamps count as the newest entry.
+                let now = log.back().map_or(now, |&(last, _)| now.max(last));
+                while let Some(&(ts, c)) = log.front() {
+                    if now - ts >= window_ms {
+                        log.pop_front();
+                        *used -= c as u64;
+                    } else {
+                        break;
+                    }
+                }
+                if *used + cost as u64 <= limit as u64 {
+                    log.push_back((now, cost));
+                    *used += cost as u64;
+                    RateLimitDecision { allowed: true, remaining: limit - *used as f64, retry_after_ms: 0.0 }
+                } else {
+                    // Wait until enough of the oldest requests leave the window.
+                    let mut freed = 0u64;
+                    let mut retry_after_ms = window_ms;
+                    for &(ts, c) in log.iter() {
+                        freed += c as u64;
+                        if *used - freed + cost as u64 <= limit as u64 {
+                            retry_after_ms = (ts + window_ms - now).max(0.0).ceil();
+                            break;
+                        }
+                    }
+                    RateLimitDecision { allowed: false, remaining: limit - *used as f64, retry_after_ms }
+                }
+            }
+        })
+    }
 
-        if self.cache.len() > self.max_size {
-            self.cache.shift_remove_index(0);
+    /// Forgets `key`, restoring its full allowance.
+    #[napi]
+    pub fn reset(&mut self, key: String) {
+        if let Some(entry) = self.keys.remove(&key) {
+            self.recency.remove(&entry.last_used);
         }
-
-        true
     }
 
     #[napi]
     pub fn clear(&mut self) {
-        self.cache.clear();
+        self.keys.clear();
+        self.recency.clear();
     }
 
     #[napi]
     pub fn size(&self) -> u32 {
-        self.cache.len() as u32
+        self.keys.len() as u32
     }
+}
 
-    fn prune(&mut self, now: f64) {
-        while let Some((_, &ts)) = self.cache.get_index(0) {
-            if now - ts > self.ttl_ms {
-                self.cache.shift_remove_index(0);
-            } else {
-                break;
-            }
-        }
-    }
+// --- END RATE LIMITING ---
+
+/// NFKC normalization shared by injection detection and near-duplicate
+/// fingerprints, folding compatibility forms (fullwidth, ligatures) together.
+fn normalize_text(text: &str) -> String {
+    text.nfkc().collect()
 }
 
 /// Robust Native Security Engine.
@@ -508,7 +5173,7 @@ impl SecurityEngine {
             return Some("PANIC: Sistema em modo de emergência".to_string());
         }
 
-        let normalized: String = text.nfkc().collect();
+        let normalized = normalize_text(&text);
 
         if let Some(index) = INJECTION_SET.matches(&normalized).iter().next() {
             let pattern = INJECTION_STRINGS.get(index).unwrap_or(&"UNKNOWN");
@@ -563,7 +5228,7 @@ impl SecurityEngine {
     fn calculate_entropy_slice(&self, chars: &[char]) -> f64 {
         if chars.is_empty() { return 0.0; }
         
-        let mut frequencies = std::collections::HashMap::with_capacity(chars.len());
+        let mut frequencies = HashMap::with_capacity(chars.len());
         for &c in chars {
             *frequencies.entry(c).or_insert(0) += 1;
         }
@@ -579,6 +5244,12 @@ impl SecurityEngine {
     }
 }
 
+impl Default for SecurityEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Native Heartbeat Manager for sub-millisecond precision tasks.
 #[napi]
 pub struct HeartbeatManager {
@@ -702,3 +5373,6 @@ impl D2LEngine {
         sum
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,139 @@
+use super::{glide, pcm16};
+use crate::*;
+
+fn contour(pitches: &[f64]) -> PhraseStats {
+    PhraseStats { pitches: pitches.to_vec(), ..Default::default() }
+}
+
+fn linear(from: f64, to: f64, points: usize) -> Vec<f64> {
+    (0..points).map(|i| from + (to - from) * i as f64 / (points - 1) as f64).collect()
+}
+
+/// Feeds `phrase` and a trailing pause; returns the first non-`none` cue.
+fn classify(engine: &mut BackchannelEngine, phrase: &[f32]) -> Option<BackchannelCue> {
+    let mut audio = phrase.to_vec();
+    audio.extend(std::iter::repeat_n(0.0, 16 * 600));
+    pcm16(&audio).chunks(640).map(|c| engine.cue(c)).find(|cue| cue.kind != BackchannelCueKind::None)
+}
+
+#[test]
+fn yields_once_at_the_end_of_a_falling_phrase() {
+    let mut engine = BackchannelEngine::new(20, 0).unwrap();
+    let mut audio = glide(200.0, 110.0, 6_000.0, 2_000);
+    audio.extend(std::iter::repeat_n(0.0, 16 * 600));
+    let yields: Vec<usize> = pcm16(&audio)
+        .chunks(640)
+        .enumerate()
+        .filter_map(|(i, c)| engine.step(c).map(|_| i))
+        .collect();
+    assert_eq!(yields.len(), 1, "{yields:?}");
+    // The pause starts with chunk 100; chunk 109 ends `minPauseMs` (200 ms) into it.
+    assert!(yields[0] >= 109, "{yields:?}");
+}
+
+#[test]
+fn pitch_rise_follows_time_order() {
+    assert_eq!(contour(&linear(200.0, 110.0, 40)).pitch_rise(), 0.0);
+    // The median smoothing trims one point at each end.
+    let rising = linear(110.0, 200.0, 40);
+    assert!((contour(&rising).pitch_rise() - semitones(rising[1], rising[38])).abs() < 1e-9);
+
+    // A question-like rise followed by a fall keeps the rise.
+    let mut hat = linear(120.0, 180.0, 20);
+    hat.extend(linear(180.0, 100.0, 20));
+    assert!((contour(&hat).pitch_rise() - semitones(hat[1], 180.0)).abs() < 1e-9);
+}
+
+#[test]
+fn pitch_rise_ignores_single_frame_octave_errors() {
+    let mut pitches = linear(200.0, 110.0, 40);
+    pitches[20] *= 2.0;
+    assert_eq!(contour(&pitches).pitch_rise(), 0.0);
+    assert_eq!(contour(&[150.0, 300.0]).pitch_rise(), 0.0);
+}
+
+#[test]
+fn falling_phrases_are_not_surprise() {
+    let mut engine = BackchannelEngine::new(20, 0).unwrap();
+    let cue = classify(&mut engine, &glide(200.0, 110.0, 6_000.0, 2_000)).expect("falling phrase yields");
+    assert_eq!(cue.kind, BackchannelCueKind::Agreement);
+    assert_eq!(cue.pitch_rise_semitones, 0.0);
+    assert!(cue.pitch_fall_semitones > CUE_AGREEMENT_MIN_FALL);
+}
+
+#[test]
+fn rising_phrases_are_surprise() {
+    let options = BackchannelOptions { require_falling_pitch: Some(false), ..Default::default() };
+    let mut engine = BackchannelEngine::with_options(BackchannelOptions { history_limit: Some(20), cooldown_ms: Some(0), ..options }).unwrap();
+    let cue = classify(&mut engine, &glide(110.0, 200.0, 6_000.0, 2_000)).expect("rising phrase yields");
+    assert_eq!(cue.kind, BackchannelCueKind::Surprise);
+    assert!(cue.pitch_rise_semitones > CUE_SURPRISE_PITCH_RISE);
+}
+
+fn energy_drop(engine: &mut BackchannelEngine) -> bool {
+    let mut fired = false;
+    for rms in [2_000.0; 10].into_iter().chain([100.0]) {
+        fired |= engine.process_energy(rms);
+    }
+    fired
+}
+
+#[test]
+fn with_options_takes_everything_from_the_options() {
+    let engine = BackchannelEngine::with_options(BackchannelOptions::default()).unwrap();
+    let options = engine.get_options();
+    assert_eq!((options.history_limit, options.cooldown_ms), (Some(10), Some(3_000)));
+
+    let engine = BackchannelEngine::with_options(BackchannelOptions {
+        history_limit: Some(4),
+        cooldown_ms: Some(250),
+        ..Default::default()
+    })
+    .unwrap();
+    let options = engine.get_options();
+    assert_eq!((options.history_limit, options.cooldown_ms), (Some(4), Some(250)));
+
+    let invalid = BackchannelOptions { drop_ratio: Some(0.0), ..Default::default() };
+    assert!(BackchannelEngine::with_options(invalid).is_err());
+}
+
+#[test]
+fn set_options_rejects_empty_history_and_non_finite_values() {
+    assert!(BackchannelEngine::new(0, 0).is_err());
+    let mut engine = BackchannelEngine::new(20, 0).unwrap();
+    let rejected = [
+        BackchannelOptions { history_limit: Some(0), ..Default::default() },
+        BackchannelOptions { pitch_fall_semitones: Some(f64::NAN), ..Default::default() },
+        BackchannelOptions { pitch_fall_semitones: Some(f64::INFINITY), ..Default::default() },
+        BackchannelOptions { min_active_energy: Some(f64::INFINITY), ..Default::default() },
+        BackchannelOptions { drop_ratio: Some(f64::NAN), ..Default::default() },
+    ];
+    for options in rejected {
+        assert!(engine.set_options(options).is_err());
+    }
+    // Rejected options leave the engine as it was.
+    assert_eq!(engine.get_options().history_limit, Some(20));
+}
+
+#[test]
+fn reset_clears_the_cooldown() {
+    let mut engine = BackchannelEngine::new(10, 60_000).unwrap();
+    assert!(energy_drop(&mut engine));
+    assert!(!energy_drop(&mut engine));
+    engine.reset();
+    assert!(energy_drop(&mut engine));
+}
+
+#[test]
+fn reset_clears_the_pause_state() {
+    let mut engine = BackchannelEngine::new(20, 0).unwrap();
+    assert!(classify(&mut engine, &glide(200.0, 110.0, 6_000.0, 2_000)).is_some());
+    assert!(engine.pause_signaled && engine.pause_drop && engine.last_signal_position.is_some());
+
+    engine.reset();
+    assert!(!engine.pause_signaled && !engine.pause_drop);
+    assert!(engine.last_signal_time.is_none() && engine.last_signal_position.is_none());
+    assert_eq!(engine.pause_ms(), 0.0);
+    // The next phrase yields again as if freshly constructed.
+    assert!(classify(&mut engine, &glide(200.0, 110.0, 6_000.0, 2_000)).is_some());
+}
@@ -0,0 +1,66 @@
+use super::pcm16;
+use super::vad::audio;
+use crate::*;
+
+fn feed(detector: &mut BargeInDetector, samples: &[f32]) -> Vec<BargeInEvent> {
+    pcm16(samples).chunks(640).map(|c| detector.step(c)).collect()
+}
+
+fn speaking() -> BargeInDetector {
+    let mut detector = BargeInDetector::new(None).unwrap();
+    detector.set_assistant_speaking(true);
+    detector
+}
+
+fn first(events: &[BargeInEvent], kind: BargeInEvent) -> Option<usize> {
+    events.iter().position(|&e| e == kind)
+}
+
+#[test]
+fn ignores_the_user_while_the_assistant_is_silent() {
+    let mut detector = BargeInDetector::new(None).unwrap();
+    let events = feed(&mut detector, &audio(&[(true, 600)]));
+    assert!(events.iter().all(|&e| e == BargeInEvent::Inactive));
+}
+
+#[test]
+fn sustained_speech_interrupts_once() {
+    let mut detector = speaking();
+    let events = feed(&mut detector, &audio(&[(false, 100), (true, 800), (false, 400)]));
+    // 500 ms of speech from 100 ms completes with the chunk ending at 600 ms.
+    assert_eq!(first(&events, BargeInEvent::Interrupt), Some(29));
+    assert_eq!(events.iter().filter(|&&e| e == BargeInEvent::Interrupt).count(), 1);
+    assert!(events[5..29].iter().all(|&e| e == BargeInEvent::Listening));
+    assert!(events[30..45].iter().all(|&e| e == BargeInEvent::Talking));
+    assert_eq!(events.last(), Some(&BargeInEvent::Idle));
+}
+
+#[test]
+fn short_bursts_are_backchannels() {
+    let mut detector = speaking();
+    let events = feed(&mut detector, &audio(&[(false, 100), (true, 200), (false, 600)]));
+    assert_eq!(first(&events, BargeInEvent::Interrupt), None);
+    assert_eq!(events.iter().filter(|&&e| e == BargeInEvent::Backchannel).count(), 1);
+    assert!(first(&events, BargeInEvent::Backchannel).unwrap() >= 15);
+}
+
+#[test]
+fn quiet_speech_stays_below_the_stricter_threshold() {
+    let quiet: Vec<f32> = audio(&[(true, 800)]).iter().map(|s| s * 0.15).collect();
+    let mut detector = speaking();
+    assert!(feed(&mut detector, &quiet).iter().all(|&e| e == BargeInEvent::Idle));
+}
+
+#[test]
+fn bursts_are_measured_from_the_start_of_playback() {
+    let mut detector = BargeInDetector::new(None).unwrap();
+    feed(&mut detector, &audio(&[(true, 300)]));
+    detector.set_assistant_speaking(true);
+    let events = feed(&mut detector, &audio(&[(true, 700)]));
+    assert_eq!(first(&events, BargeInEvent::Interrupt), Some(24));
+    assert_eq!(detector.burst_ms(), 700.0);
+
+    detector.set_assistant_speaking(false);
+    assert_eq!(detector.burst_ms(), 0.0);
+    assert_eq!(feed(&mut detector, &audio(&[(true, 20)])), vec![BargeInEvent::Inactive]);
+}
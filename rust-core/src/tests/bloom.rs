@@ -0,0 +1,79 @@
+use crate::*;
+
+fn bloom(ttl_ms: u32, expected_items: u32, false_positive_rate: f64, partitions: u32) -> RatchetBloomDedupe {
+    RatchetBloomDedupe::new(
+        ttl_ms,
+        Some(BloomDedupeOptions {
+            expected_items: Some(expected_items),
+            false_positive_rate: Some(false_positive_rate),
+            partitions: Some(partitions),
+        }),
+    )
+    .unwrap()
+}
+
+#[test]
+fn filters_are_sized_for_the_target_rate() {
+    let filter = bloom(1_000, 1_000, 0.01, 4);
+    let stats = filter.stats();
+    // -n ln(p / partitions) / ln(2)^2, rounded up to whole words.
+    assert_eq!(stats.bits_per_partition, 12_480.0);
+    assert_eq!((stats.hash_functions, stats.partitions), (9, 4));
+    assert_eq!((stats.fill_ratio, stats.estimated_items, stats.estimated_false_positive_rate), (0.0, 0.0, 0.0));
+    assert_eq!(bloom(1_000, 1, 0.5, 1).stats().partitions, 2);
+}
+
+#[test]
+fn invalid_options_are_rejected() {
+    for rate in [0.0, 1.0, -0.1, f64::NAN] {
+        assert!(RatchetBloomDedupe::new(1_000, Some(BloomDedupeOptions { false_positive_rate: Some(rate), ..Default::default() })).is_err());
+    }
+    let huge = BloomDedupeOptions { expected_items: Some(u32::MAX), false_positive_rate: Some(1e-9), partitions: Some(64) };
+    assert!(RatchetBloomDedupe::new(1_000, Some(huge)).err().unwrap().reason.contains("256 MiB"));
+}
+
+#[test]
+fn keys_are_remembered_for_the_ttl_and_then_forgotten() {
+    // Four partitions over 3 s: one slice per second.
+    let mut filter = bloom(3_000, 1_000, 0.001, 4);
+    assert!(filter.check("a".into(), Some(0.0)));
+    assert!(!filter.check("a".into(), Some(2_999.0)));
+    // Remembered for at most ttl * partitions / (partitions - 1).
+    assert!(!filter.check("a".into(), Some(3_999.0)));
+    assert!(filter.check("a".into(), Some(4_000.0)));
+
+    // Late timestamps are filed under the newest slice.
+    assert!(filter.check("late".into(), Some(0.0)));
+    assert!(!filter.check("late".into(), Some(7_999.0)));
+    filter.clear();
+    assert!(filter.check("late".into(), Some(8_000.0)));
+}
+
+#[test]
+fn false_positives_stay_near_the_target() {
+    let mut filter = bloom(60_000, 10_000, 0.01, 4);
+    let inserted = (0..10_000).filter(|i| filter.check(format!("seen-{}", i), Some(0.0))).count();
+    assert!(inserted > 9_950, "{} inserted", inserted);
+    // No false negatives.
+    assert!((0..10_000).all(|i| !filter.check(format!("seen-{}", i), Some(1.0))));
+
+    let stats = filter.stats();
+    assert!((stats.estimated_items - 10_000.0).abs() < 300.0, "{}", stats.estimated_items);
+    assert!(stats.estimated_false_positive_rate < 0.01, "{}", stats.estimated_false_positive_rate);
+    // Probes are recorded too, so keep them few relative to the load.
+    let false_positives = (0..2_000).filter(|i| !filter.check(format!("new-{}", i), Some(1.0))).count();
+    assert!(false_positives < 40, "{} false positives", false_positives);
+}
+
+#[test]
+fn overload_raises_the_reported_error_rate_instead_of_evicting() {
+    let mut filter = bloom(60_000, 100, 0.01, 2);
+    for i in 0..2_000 {
+        filter.check(format!("k{}", i), Some(0.0));
+    }
+    let stats = filter.stats();
+    assert!(stats.fill_ratio > 0.9);
+    assert!(stats.estimated_false_positive_rate > 0.5);
+    assert!(!filter.check("k0".into(), Some(1.0)));
+    assert!(!filter.check("x".repeat(MAX_KEY_LEN + 1), Some(1.0)));
+}
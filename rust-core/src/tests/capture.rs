@@ -0,0 +1,75 @@
+use super::{engine, pcm16, run_vad};
+use super::vad::audio;
+use crate::*;
+
+fn capturing(pre_roll_ms: u32, hangover_ms: u32, max_utterance_ms: u32) -> VadOptions {
+    VadOptions {
+        capture_utterances: Some(true),
+        pre_roll_ms: Some(pre_roll_ms),
+        hangover_ms: Some(hangover_ms),
+        max_utterance_ms: Some(max_utterance_ms),
+        ..Default::default()
+    }
+}
+
+fn utterances(steps: Vec<VadStep>) -> Vec<(u64, Vec<u8>)> {
+    steps.into_iter().filter_map(|s| s.utterance).collect()
+}
+
+#[test]
+fn captures_pre_roll_speech_and_hangover() {
+    let samples = audio(&[(false, 1_000), (true, 500), (false, 1_000)]);
+    let mut vad = engine(500.0, 300, capturing(300, 200, 30_000));
+    let found = utterances(run_vad(&mut vad, &samples, 20));
+
+    assert_eq!(found.len(), 1);
+    let (start, pcm) = &found[0];
+    assert_eq!(*start, 700 * 16);
+    // 300 ms pre-roll + 500 ms speech + 200 ms hangover, byte-exact.
+    assert_eq!(pcm, &pcm16(&samples[700 * 16..1_700 * 16]));
+}
+
+#[test]
+fn pre_roll_is_limited_to_the_audio_seen() {
+    let samples = audio(&[(false, 100), (true, 300), (false, 600)]);
+    let mut vad = engine(500.0, 300, capturing(300, 0, 30_000));
+    let found = utterances(run_vad(&mut vad, &samples, 20));
+    assert_eq!(found[0].0, 0);
+    assert_eq!(found[0].1.len(), 400 * 16 * 2);
+}
+
+#[test]
+fn hangover_is_bounded_by_the_decided_silence() {
+    let samples = audio(&[(false, 200), (true, 300), (false, 2_000)]);
+    let mut vad = engine(500.0, 300, capturing(0, 5_000, 30_000));
+    let found = utterances(run_vad(&mut vad, &samples, 20));
+    // Speech plus the 320 ms of silence consumed before `speech_end` was decided.
+    assert_eq!(found[0].1.len(), (300 + 320) * 16 * 2);
+}
+
+#[test]
+fn truncates_long_utterances() {
+    let samples = audio(&[(false, 200), (true, 2_000), (false, 600)]);
+    let mut vad = engine(500.0, 300, capturing(100, 200, 1_000));
+    let found = utterances(run_vad(&mut vad, &samples, 20));
+    assert_eq!(found[0].0, 100 * 16);
+    assert_eq!(found[0].1.len(), 1_000 * 16 * 2);
+}
+
+#[test]
+fn flush_and_back_to_back_utterances_keep_their_audio() {
+    let samples = audio(&[(true, 300), (false, 400), (true, 300)]);
+    let mut vad = engine(500.0, 300, capturing(100, 100, 30_000));
+    let found = utterances(run_vad(&mut vad, &samples, 20));
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0], (0, pcm16(&samples[..400 * 16])));
+    // The second pre-roll comes from the tail of the chunk that closed the first.
+    assert_eq!(found[1], (600 * 16, pcm16(&samples[600 * 16..])));
+}
+
+#[test]
+fn no_audio_is_kept_without_capture() {
+    let samples = audio(&[(false, 200), (true, 300), (false, 600)]);
+    let mut vad = engine(500.0, 300, VadOptions::default());
+    assert!(utterances(run_vad(&mut vad, &samples, 20)).is_empty());
+}
@@ -0,0 +1,107 @@
+use crate::*;
+
+const JAN_31_2026: f64 = 20_484.0 * MS_PER_DAY;
+const HOUR: f64 = 3_600_000.0;
+
+fn price(model: &str, provider: Option<&str>, input: f64, output: f64, cached: Option<f64>) -> ModelPricing {
+    ModelPricing {
+        model: model.into(),
+        provider: provider.map(Into::into),
+        input_per_million: input,
+        output_per_million: output,
+        cached_per_million: cached,
+    }
+}
+
+fn priced_engine() -> MetricsEngine {
+    let mut engine = MetricsEngine::default();
+    engine
+        .set_pricing(vec![
+            price("big", Some("acme"), 10.0, 30.0, Some(1.0)),
+            price("small", Some("acme"), 1.0, 2.0, None),
+            price("local", None, 0.5, 0.5, None),
+        ])
+        .unwrap();
+    engine
+}
+
+#[test]
+fn month_index_follows_the_civil_calendar() {
+    assert_eq!(utc_month_index(0), 1970 * 12);
+    assert_eq!(utc_month_index(-1), 1969 * 12 + 11);
+    assert_eq!(utc_month_index(20_484), 2026 * 12);
+    assert_eq!(utc_month_index(20_485), 2026 * 12 + 1);
+    assert_eq!(utc_month_index(19_782), 2024 * 12 + 1);
+}
+
+#[test]
+fn usage_is_billed_by_direction() {
+    let mut engine = priced_engine();
+    let usage = engine.record_usage("big".into(), 1_000_000, 100_000, Some(500_000), Some(JAN_31_2026)).unwrap();
+    assert!((usage.cost_usd - (10.0 + 3.0 + 0.5)).abs() < 1e-9);
+    // Without a cached price, cached tokens bill at the input rate.
+    let usage = engine.record_usage("small".into(), 0, 0, Some(1_000_000), Some(JAN_31_2026)).unwrap();
+    assert!((usage.cost_usd - 1.0).abs() < 1e-9);
+
+    let unpriced = engine.record_usage("mystery".into(), 10, 20, Some(5), Some(JAN_31_2026)).unwrap();
+    assert!(unpriced.unpriced && unpriced.cost_usd == 0.0);
+
+    engine.record_usage("local".into(), 2_000_000, 0, None, Some(JAN_31_2026)).unwrap();
+    let report = engine.cost_report(Some(JAN_31_2026)).unwrap();
+    assert!((report.total_usd - 15.5).abs() < 1e-9);
+    assert_eq!(report.unpriced_tokens, 35.0);
+    let by_provider: Vec<(&str, f64)> = report.by_provider.iter().map(|e| (e.name.as_str(), e.usd)).collect();
+    assert_eq!(by_provider, [("acme", 14.5), ("unknown", 1.0)]);
+}
+
+#[test]
+fn budgets_cross_once_and_reset_with_the_period() {
+    let mut engine = priced_engine();
+    engine.set_budget(CostBudget { daily_usd: Some(2.0), monthly_usd: Some(3.0) }).unwrap();
+    let spend = |engine: &mut MetricsEngine, at: f64| engine.record_usage("small".into(), 1_000_000, 0, None, Some(at)).unwrap();
+
+    assert!(!spend(&mut engine, JAN_31_2026).daily_budget_crossed);
+    assert!(spend(&mut engine, JAN_31_2026 + HOUR).daily_budget_crossed);
+    assert!(!spend(&mut engine, JAN_31_2026 + 2.0 * HOUR).daily_budget_crossed);
+    let report = engine.cost_report(Some(JAN_31_2026 + 3.0 * HOUR)).unwrap();
+    assert!(report.daily_budget_exceeded && report.monthly_budget_exceeded);
+
+    // February 1st starts a new day and month.
+    let report = engine.cost_report(Some(JAN_31_2026 + 25.0 * HOUR)).unwrap();
+    assert_eq!((report.daily_usd, report.monthly_usd, report.total_usd), (0.0, 0.0, 3.0));
+    assert!(!report.daily_budget_exceeded && !report.monthly_budget_exceeded);
+
+    // Late events from January are billed to the current period.
+    spend(&mut engine, JAN_31_2026);
+    assert_eq!(engine.cost_report(Some(JAN_31_2026 + 26.0 * HOUR)).unwrap().daily_usd, 1.0);
+}
+
+#[test]
+fn invalid_prices_and_budgets_are_rejected() {
+    let mut engine = MetricsEngine::default();
+    assert!(engine.set_pricing(vec![price("m", None, -1.0, 1.0, None)]).is_err());
+    assert!(engine.set_pricing(vec![price("m", None, 1.0, f64::NAN, None)]).is_err());
+    assert!(engine.set_budget(CostBudget { daily_usd: Some(f64::INFINITY), monthly_usd: None }).is_err());
+    let table: Vec<ModelPricing> = (0..=MAX_PRICED_MODELS).map(|i| price(&format!("m{}", i), None, 1.0, 1.0, None)).collect();
+    assert!(engine.set_pricing(table).is_err());
+    assert!(engine.cost.pricing.is_empty());
+}
+
+#[test]
+fn bad_timestamps_cannot_poison_the_budget_periods() {
+    let mut engine = priced_engine();
+    for ts in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let err = engine.record_usage("small".into(), 1, 1, None, Some(ts)).err().unwrap();
+        assert_eq!(err.status, napi::Status::InvalidArg);
+        assert!(engine.cost_report(Some(ts)).is_err());
+    }
+    assert_eq!(engine.cost.day, i64::MIN);
+    assert_eq!(engine.summarize().total_tokens, 0.0);
+
+    // A far-future timestamp is clamped to the allowed clock skew.
+    engine.record_usage("small".into(), 1_000_000, 0, None, Some(1e18)).unwrap();
+    let latest_day = ((unix_time_ms() + MAX_TIMESTAMP_SKEW_MS) / MS_PER_DAY).floor() as i64;
+    assert!(engine.cost.day <= latest_day);
+    engine.record_usage("small".into(), 1_000_000, 0, None, None).unwrap();
+    assert!(engine.cost_report(None).unwrap().daily_usd >= 1.0);
+}
@@ -0,0 +1,61 @@
+use super::{engine, run_vad, segments};
+use super::vad::audio;
+use crate::*;
+
+fn debounced(min_speech_ms: u32, min_voiced_chunks: u32) -> VadOptions {
+    VadOptions {
+        min_speech_ms: Some(min_speech_ms),
+        min_voiced_chunks: Some(min_voiced_chunks),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn a_single_click_is_not_speech() {
+    let samples = audio(&[(false, 200), (true, 20), (false, 600)]);
+
+    let mut plain = engine(500.0, 300, VadOptions::default());
+    assert_eq!(segments(&run_vad(&mut plain, &samples, 20)).len(), 1);
+
+    let mut vad = engine(500.0, 300, debounced(0, 3));
+    let steps = run_vad(&mut vad, &samples, 20);
+    assert!(steps.iter().all(|s| s.kind == VadEventKind::Silent));
+}
+
+#[test]
+fn confirms_speech_once_the_run_is_long_enough() {
+    let samples = audio(&[(false, 200), (true, 500), (false, 600)]);
+    let mut vad = engine(500.0, 300, debounced(100, 1));
+    let steps = run_vad(&mut vad, &samples, 20);
+
+    let start = steps.iter().find(|s| s.kind == VadEventKind::SpeechStart).unwrap();
+    // Decided on the chunk completing 100 ms of voice, backdated to its start.
+    assert_eq!(start.sample_offset, 280 * 16);
+    assert_eq!(start.segment_start_ms, Some(200.0));
+    assert_eq!(segments(&steps), vec![(200.0, 700.0)]);
+}
+
+#[test]
+fn interrupted_runs_start_over() {
+    // 60 ms bursts separated by silence never reach 80 ms of continuous voice.
+    let samples = audio(&[(true, 60), (false, 40), (true, 60), (false, 40), (true, 60), (false, 600)]);
+    let mut vad = engine(500.0, 300, debounced(80, 1));
+    assert!(segments(&run_vad(&mut vad, &samples, 20)).is_empty());
+
+    let mut chunks = engine(500.0, 300, debounced(0, 4));
+    assert!(segments(&run_vad(&mut chunks, &samples, 20)).is_empty());
+}
+
+#[test]
+fn false_starts_become_pre_roll() {
+    let samples = audio(&[(false, 200), (true, 40), (false, 60), (true, 300), (false, 600)]);
+    let options = VadOptions { capture_utterances: Some(true), pre_roll_ms: Some(300), hangover_ms: Some(0), ..debounced(100, 1) };
+    let mut vad = engine(500.0, 300, options);
+    let steps = run_vad(&mut vad, &samples, 20);
+
+    assert_eq!(segments(&steps), vec![(300.0, 600.0)]);
+    let (start, pcm) = steps.into_iter().find_map(|s| s.utterance).unwrap();
+    // Pre-roll reaches back over the discarded click into the silence before it.
+    assert_eq!(start, 0);
+    assert_eq!(pcm, super::pcm16(&samples[..600 * 16]));
+}
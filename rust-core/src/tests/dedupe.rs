@@ -0,0 +1,79 @@
+use crate::*;
+
+fn sliding() -> Option<DedupeOptions> {
+    Some(DedupeOptions { sliding_expiry: Some(true) })
+}
+
+#[test]
+fn keys_are_duplicates_until_their_ttl_passes() {
+    let mut dedupe = RatchetDedupe::new(1_000, 100, None);
+    assert!(dedupe.check("a".into(), Some(0.0), None));
+    assert!(!dedupe.check("a".into(), Some(1_000.0), None));
+    assert!(dedupe.check("a".into(), Some(1_001.0), None));
+    assert_eq!(dedupe.size(), 1);
+    dedupe.clear();
+    assert!(dedupe.expiry.is_empty() && dedupe.size() == 0);
+}
+
+#[test]
+fn per_key_ttl_overrides_the_default() {
+    let mut dedupe = RatchetDedupe::new(1_000, 100, None);
+    dedupe.check("short".into(), Some(0.0), Some(100));
+    dedupe.check("long".into(), Some(0.0), Some(10_000));
+    // Without sliding expiry a repeat does not change the stored TTL.
+    assert!(!dedupe.check("short".into(), Some(50.0), Some(10_000)));
+    assert!(dedupe.check("short".into(), Some(150.0), None));
+    assert!(!dedupe.check("long".into(), Some(5_000.0), None));
+    assert!(dedupe.check("long".into(), Some(10_001.0), None));
+}
+
+#[test]
+fn expiry_follows_deadlines_not_insertion_order() {
+    let mut dedupe = RatchetDedupe::new(1_000, 100, None);
+    dedupe.check("new".into(), Some(5_000.0), None);
+    // Inserted later with an older timestamp: expires first.
+    dedupe.check("old".into(), Some(0.0), None);
+    dedupe.check("probe".into(), Some(1_500.0), None);
+    assert!(!dedupe.cache.contains_key("old"));
+    assert!(dedupe.cache.contains_key("new"));
+    assert_eq!(dedupe.size(), 2);
+}
+
+#[test]
+fn sliding_expiry_extends_on_each_sighting() {
+    let mut dedupe = RatchetDedupe::new(1_000, 100, sliding());
+    dedupe.check("a".into(), Some(0.0), None);
+    for at in [800.0, 1_600.0, 2_400.0] {
+        assert!(!dedupe.check("a".into(), Some(at), None), "seen again at {}", at);
+    }
+    assert!(dedupe.check("a".into(), Some(3_401.0), None));
+
+    // A repeat may also replace the key's TTL; a late repeat never shortens it.
+    dedupe.check("b".into(), Some(0.0), None);
+    assert!(!dedupe.check("b".into(), Some(500.0), Some(5_000)));
+    assert!(!dedupe.check("b".into(), Some(0.0), None));
+    assert_eq!(dedupe.cache["b"].expires_at, 5_500.0);
+    assert_eq!(dedupe.expiry.len(), dedupe.cache.len());
+}
+
+#[test]
+fn capacity_evicts_the_key_closest_to_expiring() {
+    let mut dedupe = RatchetDedupe::new(1_000, 2, None);
+    dedupe.check("a".into(), Some(0.0), Some(10_000));
+    dedupe.check("b".into(), Some(0.0), Some(100));
+    dedupe.check("c".into(), Some(0.0), Some(5_000));
+    assert_eq!(dedupe.size(), 2);
+    assert!(!dedupe.cache.contains_key("b"));
+    assert!(!dedupe.check("a".into(), Some(1.0), None));
+
+    let mut empty = RatchetDedupe::new(1_000, 0, None);
+    assert!(empty.check("a".into(), Some(0.0), None));
+    assert_eq!(empty.size(), 0);
+}
+
+#[test]
+fn oversized_keys_are_rejected() {
+    let mut dedupe = RatchetDedupe::new(1_000, 10, None);
+    assert!(!dedupe.check("x".repeat(MAX_KEY_LEN + 1), Some(0.0), None));
+    assert!(dedupe.check("x".repeat(MAX_KEY_LEN), Some(0.0), None));
+}
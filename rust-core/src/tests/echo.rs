@@ -0,0 +1,136 @@
+use super::{engine, noise, pcm16, rms, segments, vowel};
+use super::vad::audio;
+use crate::*;
+
+const CHUNK: usize = 320;
+
+fn suppressor(max_delay_ms: u32) -> EchoSuppressor {
+    EchoSuppressor::new(&AudioFormat::default_pcm16(), max_delay_ms).unwrap()
+}
+
+/// Plays `far` in 20 ms chunks, hearing it back `delay` samples later at
+/// `gain` plus `near`. Returns (mic level, residual level) per chunk.
+fn play(echo: &mut EchoSuppressor, far: &[f32], near: &[f32], delay: usize, gain: f32) -> Vec<(f32, Option<f32>)> {
+    (0..far.len() / CHUNK)
+        .map(|i| {
+            echo.push(&pcm16(&far[i * CHUNK..(i + 1) * CHUNK]));
+            let mic: Vec<f32> = (i * CHUNK..(i + 1) * CHUNK)
+                .map(|t| near[t] + if t >= delay { far[t - delay] * gain } else { 0.0 })
+                .map(|s| s.round())
+                .collect();
+            (rms(&mic), echo.suppress(&mic).map(|r| rms(&r)))
+        })
+        .collect()
+}
+
+#[test]
+fn removes_delayed_playback() {
+    let far = noise(3, 8_000.0, 32_000);
+    let mut echo = suppressor(300);
+    let levels = play(&mut echo, &far, &[0.0; 32_000], 1_600, 0.6);
+
+    // Once the delay is inside the history every chunk is cancelled.
+    for &(mic, residual) in &levels[10..] {
+        assert!(residual.unwrap() < mic * 0.05, "{mic} -> {residual:?}");
+    }
+    assert_eq!(samples_to_ms(echo.last_delay as u64), 100.0);
+    assert!(echo.last_correlation > 0.99);
+}
+
+#[test]
+fn keeps_uncorrelated_speech() {
+    let far = noise(3, 8_000.0, 32_000);
+    let near = noise(4, 8_000.0, 32_000);
+    let mut echo = suppressor(300);
+    let levels = play(&mut echo, &far, &near, 1_600, 0.0);
+    assert!(levels[10..].iter().all(|&(_, residual)| residual.is_none()));
+    assert!(echo.last_correlation < ECHO_MIN_CORRELATION);
+}
+
+#[test]
+fn ignores_delays_beyond_the_search_range() {
+    let far = noise(3, 8_000.0, 32_000);
+    let mut echo = suppressor(100);
+    let levels = play(&mut echo, &far, &[0.0; 32_000], 3_200, 0.6);
+    assert!(levels[20..].iter().all(|&(_, residual)| residual.is_none()));
+}
+
+#[test]
+fn reference_pushed_ahead_of_playback_is_lost() {
+    let far = noise(3, 8_000.0, 48_000);
+    let mut echo = suppressor(300);
+    // The whole clip is pushed at synthesis time, 3 s before it is heard.
+    echo.push(&pcm16(&far));
+    let mic: Vec<f32> = far[..CHUNK].iter().map(|s| (s * 0.6).round()).collect();
+    assert!(echo.suppress(&mic).is_none());
+}
+
+#[test]
+fn vad_does_not_hear_its_own_playback() {
+    let tts = vowel(180.0, 12, 4_000.0, 48_000);
+    let user = audio(&[(false, 1_500), (true, 800), (false, 700)]);
+    let options = VadOptions { echo_max_delay_ms: Some(200), ..Default::default() };
+    let mut with_reference = engine(800.0, 300, options.clone());
+    let mut without = engine(800.0, 300, options);
+    let (mut heard, mut echoed) = (Vec::new(), Vec::new());
+    for i in 0..tts.len() / CHUNK {
+        let range = i * CHUNK..(i + 1) * CHUNK;
+        let mic: Vec<f32> = range
+            .clone()
+            .map(|t| user[t] + if t >= 960 { tts[t - 960] * 0.5 } else { 0.0 })
+            .collect();
+        with_reference.feed_reference(&pcm16(&tts[range]));
+        heard.push(with_reference.step(&pcm16(&mic)));
+        echoed.push(without.step(&pcm16(&mic)));
+    }
+    heard.push(with_reference.finish());
+    echoed.push(without.finish());
+
+    // Without a reference the playback itself is one long segment.
+    assert_eq!(segments(&echoed), vec![(60.0, 3_000.0)]);
+    let found = segments(&heard);
+    assert_eq!(found.len(), 1, "{found:?}");
+    assert!((found[0].0 - 1_500.0).abs() <= 20.0 && (found[0].1 - 2_300.0).abs() <= 20.0, "{found:?}");
+}
+
+#[test]
+fn stops_searching_once_playback_stops() {
+    let far = noise(3, 8_000.0, 16_000);
+    let mut echo = suppressor(100);
+    play(&mut echo, &far, &[0.0; 16_000], 800, 0.6);
+    assert!(echo.last_correlation > 0.99);
+
+    // The tail of the playback can still echo for up to the search range.
+    let tail: Vec<f32> = far[far.len() - 800..far.len() - 800 + CHUNK].iter().map(|s| (s * 0.6).round()).collect();
+    assert!(echo.suppress(&tail).is_some());
+    let mic = noise(5, 2_000.0, CHUNK);
+    for _ in 0..5 {
+        echo.suppress(&mic);
+    }
+    assert!(echo.history.is_empty());
+    assert!(echo.suppress(&mic).is_none());
+    assert_eq!(echo.last_correlation, 0.0);
+}
+
+#[test]
+fn skips_silent_playback() {
+    let mut echo = suppressor(300);
+    echo.push(&pcm16(&[0.0; 8_000]));
+    assert!(echo.suppress(&noise(5, 2_000.0, CHUNK)).is_none());
+    assert_eq!(echo.last_correlation, 0.0);
+    assert_eq!(echo.history.len(), 8_000);
+}
+
+#[test]
+fn reports_the_raw_level_next_to_the_residual() {
+    let far = noise(3, 8_000.0, 16_000);
+    let mut vad = engine(800.0, 300, VadOptions { echo_max_delay_ms: Some(100), ..Default::default() });
+    for i in 0..far.len() / CHUNK {
+        vad.feed_reference(&pcm16(&far[i * CHUNK..(i + 1) * CHUNK]));
+        let mic: Vec<f32> = (i * CHUNK..(i + 1) * CHUNK).map(|t| if t >= 800 { far[t - 800] * 0.6 } else { 0.0 }).collect();
+        vad.step(&pcm16(&mic));
+    }
+    let raw = 8_000.0 / 3f64.sqrt() * 0.6;
+    assert!((vad.last_rms() - raw).abs() < raw * 0.1, "{}", vad.last_rms());
+    assert!(vad.last_residual_rms() < raw * 0.05, "{}", vad.last_residual_rms());
+}
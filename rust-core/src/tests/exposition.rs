@@ -0,0 +1,81 @@
+use crate::*;
+
+fn labels(pairs: &[(&str, &str)]) -> Option<HashMap<String, String>> {
+    Some(pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect())
+}
+
+fn engine_with_samples() -> MetricsEngine {
+    let mut engine = MetricsEngine::default();
+    engine
+        .define_metric(MetricDescriptor {
+            name: "requests_total".into(),
+            kind: MetricKind::Counter,
+            help: Some("Requests\nserved".into()),
+            buckets: None,
+            max_series: None,
+        })
+        .unwrap();
+    engine.increment_counter("requests_total".into(), Some(3.0), labels(&[("path", "/a\"b\\")])).unwrap();
+    engine
+        .define_metric(MetricDescriptor {
+            name: "wait_ms".into(),
+            kind: MetricKind::Histogram,
+            help: None,
+            buckets: Some(vec![10.0, 100.0]),
+            max_series: None,
+        })
+        .unwrap();
+    for v in [5.0, 50.0, 500.0] {
+        engine.observe_histogram("wait_ms".into(), v, labels(&[("channel", "voice")])).unwrap();
+    }
+    engine.set_gauge("temperature".into(), f64::NEG_INFINITY, None).unwrap();
+    engine
+}
+
+#[test]
+fn reserved_label_names_are_rejected() {
+    let mut engine = MetricsEngine::default();
+    for name in ["le", "quantile"] {
+        let err = engine.increment_counter("calls".into(), None, labels(&[(name, "1")])).unwrap_err();
+        assert_eq!(err.status, napi::Status::InvalidArg);
+        assert!(err.reason.contains(name));
+        assert!(engine.record_latency(1.0, labels(&[(name, "1")]), None, None).is_err());
+    }
+    assert!(engine.increment_counter("calls".into(), None, labels(&[("level", "1")])).is_ok());
+}
+
+#[test]
+fn prometheus_text_format() {
+    let text = engine_with_samples().render_prometheus(None);
+    assert!(text.contains("# HELP requests_total Requests\\nserved\n# TYPE requests_total counter\n"));
+    assert!(text.contains("requests_total{path=\"/a\\\"b\\\\\"} 3\n"));
+    assert!(text.contains("# TYPE wait_ms histogram\n"));
+    assert!(text.contains("wait_ms_bucket{channel=\"voice\",le=\"10\"} 1\n"));
+    assert!(text.contains("wait_ms_bucket{channel=\"voice\",le=\"100\"} 2\n"));
+    assert!(text.contains("wait_ms_bucket{channel=\"voice\",le=\"+Inf\"} 3\n"));
+    assert!(text.contains("wait_ms_sum{channel=\"voice\"} 555\n"));
+    assert!(text.contains("wait_ms_count{channel=\"voice\"} 3\n"));
+    assert!(text.contains("temperature -Inf\n"));
+    assert!(!text.contains("# EOF"));
+    assert!(!text.contains("ratchet_dropped_series"));
+}
+
+#[test]
+fn open_metrics_format() {
+    let text = engine_with_samples().render_prometheus(Some(true));
+    assert!(text.contains("# TYPE requests counter\nrequests_total{"));
+    assert!(text.contains("# TYPE ratchet_tokens counter\n"));
+    assert!(text.ends_with("# EOF\n"));
+}
+
+#[test]
+fn dropped_metric_names_are_exported() {
+    let mut engine = MetricsEngine::default();
+    while engine.metrics.len() < MAX_METRICS {
+        let name = format!("m{}", engine.metrics.len());
+        engine.increment_counter(name, None, None).unwrap();
+    }
+    assert!(!engine.increment_counter("overflow".into(), None, None).unwrap());
+    let text = engine.render_prometheus(Some(true));
+    assert!(text.contains("# TYPE ratchet_dropped_series counter\nratchet_dropped_series_total 1\n"));
+}
@@ -0,0 +1,76 @@
+use crate::*;
+
+const MINUTE: f64 = 60_000.0;
+const HOUR: f64 = 3_600_000.0;
+/// 2026-01-31T00:00Z.
+const BASE: f64 = 20_484.0 * MS_PER_DAY;
+
+#[test]
+fn events_land_in_every_resolution() {
+    let mut engine = MetricsEngine::default();
+    engine.record_tokens("m".into(), 100, None, Some(BASE + 10_000.0)).unwrap();
+    engine.record_tokens("m".into(), 50, None, Some(BASE + MINUTE + 1.0)).unwrap();
+    engine.record_latency(40.0, None, Some(BASE + 20_000.0), None).unwrap();
+    engine.record_latency(80.0, None, Some(BASE + 30_000.0), None).unwrap();
+
+    let minutes = engine.history(HistoryResolution::Minute, BASE, BASE + HOUR);
+    assert_eq!(minutes.len(), 2);
+    assert_eq!((minutes[0].start_ms, minutes[0].tokens), (BASE, 100.0));
+    assert_eq!((minutes[0].latency_count, minutes[0].avg_latency_ms), (2.0, 60.0));
+    assert_eq!((minutes[0].min_latency_ms, minutes[0].max_latency_ms), (40.0, 80.0));
+    assert_eq!((minutes[1].start_ms, minutes[1].tokens, minutes[1].avg_latency_ms), (BASE + MINUTE, 50.0, 0.0));
+
+    for resolution in [HistoryResolution::Hour, HistoryResolution::Day] {
+        let points = engine.history(resolution, BASE, BASE);
+        assert_eq!(points.len(), 1);
+        assert_eq!((points[0].tokens, points[0].latency_count), (150.0, 2.0));
+    }
+}
+
+#[test]
+fn ranges_select_overlapping_buckets() {
+    let mut engine = MetricsEngine::default();
+    for i in 0..10 {
+        engine.record_tokens("m".into(), 1, None, Some(BASE + i as f64 * MINUTE)).unwrap();
+    }
+    // A bucket overlaps when it ends after `from` and starts at or before `to`.
+    let points = engine.history(HistoryResolution::Minute, BASE + 2.5 * MINUTE, BASE + 5.0 * MINUTE);
+    let starts: Vec<f64> = points.iter().map(|p| (p.start_ms - BASE) / MINUTE).collect();
+    assert_eq!(starts, [2.0, 3.0, 4.0, 5.0]);
+}
+
+#[test]
+fn old_buckets_age_out_of_the_ring() {
+    let mut engine = MetricsEngine::default();
+    engine.record_tokens("m".into(), 1, None, Some(BASE)).unwrap();
+    engine.record_tokens("m".into(), 2, None, Some(BASE + 25.0 * HOUR)).unwrap();
+    // The minute ring keeps 24 hours; the hour ring still has both.
+    let minutes = engine.history(HistoryResolution::Minute, 0.0, f64::MAX);
+    assert_eq!(minutes.iter().map(|p| p.tokens).collect::<Vec<_>>(), [2.0]);
+    assert_eq!(engine.history(HistoryResolution::Hour, 0.0, f64::MAX).len(), 2);
+
+    // Events older than the retention window are dropped, not misfiled.
+    engine.record_tokens("m".into(), 4, None, Some(BASE - HOUR)).unwrap();
+    assert_eq!(engine.history(HistoryResolution::Minute, 0.0, f64::MAX).len(), 1);
+    assert_eq!(engine.history(HistoryResolution::Hour, 0.0, f64::MAX).len(), 3);
+}
+
+#[test]
+fn bad_timestamps_cannot_poison_the_rings() {
+    let mut engine = MetricsEngine::default();
+    for ts in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        assert!(engine.record_tokens("m".into(), 1, None, Some(ts)).is_err());
+        assert!(engine.record_latency(1.0, None, Some(ts), None).is_err());
+    }
+    assert!(engine.history.rings.iter().all(|ring| ring.latest == i64::MIN));
+
+    // A far-future timestamp lands at most one skew window ahead, so
+    // events recorded at the current time are still retained.
+    engine.record_tokens("m".into(), 1, None, Some(9.0e18)).unwrap();
+    let latest = ((unix_time_ms() + MAX_TIMESTAMP_SKEW_MS) / MINUTE).floor() as i64;
+    assert!(engine.history.rings[0].latest <= latest);
+    engine.record_tokens("m".into(), 2, None, None).unwrap();
+    let now = unix_time_ms();
+    let recent = engine.history(HistoryResolution::Minute, now - HOUR, now + HOUR);
+    assert_eq!(recent.iter().map(|p| p.tokens).sum::<f64>(), 3.0);
+}
@@ -0,0 +1,61 @@
+use crate::*;
+
+fn record_all(engine: &mut MetricsEngine, samples: impl IntoIterator<Item = f64>) {
+    for ms in samples {
+        engine.record_latency(ms, None, Some(0.0), None).unwrap();
+    }
+}
+
+#[test]
+fn percentile_interpolates_between_ranks() {
+    let sorted = [10.0, 20.0, 30.0, 40.0];
+    assert_eq!(percentile(&sorted, 0.0), 10.0);
+    assert_eq!(percentile(&sorted, 50.0), 25.0);
+    assert_eq!(percentile(&sorted, 100.0), 40.0);
+    assert!((percentile(&sorted, 90.0) - 37.0).abs() < 1e-9);
+    assert_eq!(percentile(&[7.0], 99.0), 7.0);
+}
+
+#[test]
+fn summary_reports_tail_latency() {
+    let mut engine = MetricsEngine::new(Some(1_000));
+    // 1..=100 in shuffled order: ordering must not matter.
+    record_all(&mut engine, (0..100).map(|i| ((i * 37) % 100 + 1) as f64));
+    let summary = engine.summarize();
+    assert_eq!(summary.latency_samples, 100);
+    assert_eq!((summary.min_latency_ms, summary.max_latency_ms), (1.0, 100.0));
+    assert_eq!(summary.avg_latency_ms, 50.5);
+    assert!((summary.p50_latency_ms - 50.5).abs() < 1e-9);
+    assert!((summary.p90_latency_ms - 90.1).abs() < 1e-9);
+    assert!((summary.p95_latency_ms - 95.05).abs() < 1e-9);
+    assert!((summary.p99_latency_ms - 99.01).abs() < 1e-9);
+    // Population standard deviation of 1..=100.
+    assert!((summary.latency_std_dev_ms - 28.866_070_047_722_12).abs() < 1e-9);
+}
+
+#[test]
+fn summary_covers_only_the_configured_window() {
+    let mut engine = MetricsEngine::new(Some(10));
+    record_all(&mut engine, (1..=25).map(f64::from));
+    let summary = engine.summarize();
+    assert_eq!(summary.latency_samples, 10);
+    assert_eq!((summary.min_latency_ms, summary.max_latency_ms), (16.0, 25.0));
+    assert_eq!(summary.avg_latency_ms, 20.5);
+
+    assert_eq!(MetricsEngine::new(None).latency_window, MAX_LATENCY_SAMPLES);
+    assert_eq!(MetricsEngine::new(Some(0)).latency_window, 1);
+    assert_eq!(MetricsEngine::new(Some(u32::MAX)).latency_window, MAX_LATENCY_WINDOW);
+}
+
+#[test]
+fn empty_and_non_finite_samples_report_zeros() {
+    let mut engine = MetricsEngine::default();
+    assert!(!engine.record_latency(f64::NAN, None, None, None).unwrap());
+    assert!(!engine.record_latency(f64::INFINITY, None, None, None).unwrap());
+    let summary = engine.summarize();
+    assert_eq!(summary.latency_samples, 0);
+    assert_eq!(
+        [summary.avg_latency_ms, summary.min_latency_ms, summary.p99_latency_ms, summary.latency_std_dev_ms],
+        [0.0; 4]
+    );
+}
@@ -0,0 +1,123 @@
+use crate::*;
+
+fn labels(pairs: &[(&str, &str)]) -> Option<HashMap<String, String>> {
+    Some(pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect())
+}
+
+fn series_count(engine: &MetricsEngine, name: &str) -> usize {
+    engine.metrics[name].series.len()
+}
+
+#[test]
+fn labels_address_one_series_regardless_of_order() {
+    let mut engine = MetricsEngine::default();
+    engine.increment_counter("calls_total".into(), None, labels(&[("a", "1"), ("b", "2")])).unwrap();
+    engine.increment_counter("calls_total".into(), Some(2.0), labels(&[("b", "2"), ("a", "1")])).unwrap();
+    let family = engine.collect().into_iter().find(|f| f.name == "calls_total").unwrap();
+    assert_eq!(family.series.len(), 1);
+    assert_eq!(family.series[0].value, 3.0);
+}
+
+#[test]
+fn gauges_and_histograms_accumulate() {
+    let mut engine = MetricsEngine::default();
+    engine.set_gauge("queue_depth".into(), 5.0, None).unwrap();
+    engine.add_gauge("queue_depth".into(), -2.0, None).unwrap();
+    engine
+        .define_metric(MetricDescriptor {
+            name: "size_bytes".into(),
+            kind: MetricKind::Histogram,
+            help: None,
+            buckets: Some(vec![100.0, 10.0, f64::NAN, 10.0]),
+            max_series: None,
+        })
+        .unwrap();
+    for v in [5.0, 10.0, 50.0, 500.0] {
+        engine.observe_histogram("size_bytes".into(), v, None).unwrap();
+    }
+    let families = engine.collect();
+    let gauge = families.iter().find(|f| f.name == "queue_depth").unwrap();
+    assert_eq!(gauge.series[0].value, 3.0);
+    let histogram = families.iter().find(|f| f.name == "size_bytes").unwrap();
+    assert_eq!(histogram.buckets, Some(vec![10.0, 100.0]));
+    assert_eq!(histogram.series[0].bucket_counts, Some(vec![2.0, 3.0, 4.0]));
+    assert_eq!((histogram.series[0].value, histogram.series[0].count), (565.0, Some(4.0)));
+}
+
+#[test]
+fn kind_mismatches_and_bad_values_are_rejected() {
+    let mut engine = MetricsEngine::default();
+    engine.set_gauge("level".into(), 1.0, None).unwrap();
+    assert!(engine.increment_counter("level".into(), None, None).is_err());
+    assert!(engine.increment_counter("calls".into(), Some(-1.0), None).is_err());
+    assert!(engine.increment_counter("9calls".into(), None, None).is_err());
+    assert!(engine.increment_counter("calls".into(), None, labels(&[("__name", "x")])).is_err());
+    let redefine = MetricDescriptor { name: "level".into(), kind: MetricKind::Counter, help: None, buckets: None, max_series: None };
+    assert!(engine.define_metric(redefine).is_err());
+}
+
+#[test]
+fn cardinality_limit_reports_dropped_series() {
+    let mut engine = MetricsEngine::default();
+    engine
+        .define_metric(MetricDescriptor { name: "per_user".into(), kind: MetricKind::Counter, help: None, buckets: None, max_series: Some(2) })
+        .unwrap();
+    let accepted: Vec<bool> = ["a", "b", "c", "a"]
+        .iter()
+        .map(|user| engine.increment_counter("per_user".into(), None, labels(&[("user", user)])).unwrap())
+        .collect();
+    assert_eq!(accepted, [true, true, false, true]);
+    assert_eq!(series_count(&engine, "per_user"), 2);
+    assert_eq!(engine.summarize().dropped_series, 1.0);
+    assert!(engine.render_prometheus(None).contains("ratchet_dropped_series_total{metric=\"per_user\"} 1\n"));
+}
+
+#[test]
+fn token_totals_survive_the_series_cap() {
+    let mut engine = MetricsEngine::default();
+    let models: Vec<String> = (0..40).map(|i| format!("model-{}", i)).collect();
+    engine
+        .set_pricing(
+            models
+                .iter()
+                .map(|m| ModelPricing { model: m.clone(), provider: None, input_per_million: 1.0, output_per_million: 2.0, cached_per_million: None })
+                .collect(),
+        )
+        .unwrap();
+    for model in &models {
+        let usage = engine.record_usage(model.clone(), 1_000, 500, Some(100), None).unwrap();
+        assert!(!usage.unpriced);
+    }
+    // Three direction series per model overflow the default 50-series cap...
+    assert_eq!(series_count(&engine, TOKENS_METRIC), DEFAULT_MAX_SERIES);
+    // ...but the summary still counts every billed token.
+    let summary = engine.summarize();
+    assert_eq!(summary.total_tokens, 40.0 * 1_600.0);
+    assert_eq!(summary.model_breakdown.len(), 40);
+    assert!(summary.model_breakdown.iter().all(|m| m.count == 1_600.0));
+}
+
+#[test]
+fn token_breakdown_is_bounded_but_the_total_is_not() {
+    let mut engine = MetricsEngine::default();
+    for i in 0..MAX_TRACKED_MODELS + 10 {
+        engine.record_tokens(format!("m{}", i), 10, None, None).unwrap();
+    }
+    let summary = engine.summarize();
+    assert_eq!(summary.model_breakdown.len(), MAX_TRACKED_MODELS);
+    assert_eq!(summary.total_tokens, (MAX_TRACKED_MODELS + 10) as f64 * 10.0);
+}
+
+#[test]
+fn rejected_labels_leave_the_engine_unchanged() {
+    let mut engine = MetricsEngine::default();
+    engine.record_tokens("gpt".into(), 10, None, Some(1_000.0)).unwrap();
+    engine.record_latency(20.0, None, Some(1_000.0), None).unwrap();
+    let before = engine.encode_snapshot();
+
+    assert!(engine.record_tokens("gpt".into(), 10, labels(&[("le", "x")]), Some(1_000.0)).is_err());
+    assert!(engine.record_latency(50.0, labels(&[("quantile", "x")]), Some(1_000.0), None).is_err());
+    assert_eq!(engine.encode_snapshot(), before);
+    let summary = engine.summarize();
+    assert_eq!((summary.total_tokens, summary.latency_samples), (10.0, 1));
+}
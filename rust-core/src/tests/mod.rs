@@ -0,0 +1,120 @@
+//! Native unit tests for the pure-Rust parts of the crate. Anything that
+//! creates a napi `Buffer` needs a live Node runtime and is covered by the
+//! vitest suites under `src/` instead.
+
+use crate::{VadEngine, VadEventKind, VadOptions, VadStep};
+
+mod backchannel;
+mod barge_in;
+mod bloom;
+mod capture;
+mod cost;
+mod debounce;
+mod dedupe;
+mod echo;
+mod exposition;
+mod history;
+mod latency;
+mod metrics;
+mod near_dedupe;
+mod noise_floor;
+mod otlp;
+mod pcm;
+mod pitch;
+mod rate_limit;
+mod snapshot;
+mod spectral;
+mod turn_taking;
+mod vad;
+mod wav;
+
+/// Deterministic xorshift noise in `[-amplitude, amplitude]`.
+pub(crate) fn noise(seed: u64, amplitude: f32, len: usize) -> Vec<f32> {
+    let mut state = seed.max(1);
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            ((state >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0) as f32 * amplitude
+        })
+        .collect()
+}
+
+/// Sine tone of `len` samples at `sample_rate`.
+pub(crate) fn tone(freq: f32, amplitude: f32, len: usize, sample_rate: u32) -> Vec<f32> {
+    (0..len)
+        .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32).sin() * amplitude)
+        .collect()
+}
+
+/// Encodes samples already in the i16 range as little-endian PCM16.
+pub(crate) fn pcm16(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|&s| (s.round().clamp(-32_768.0, 32_767.0) as i16).to_le_bytes())
+        .collect()
+}
+
+pub(crate) fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32).sqrt()
+}
+
+/// Synthetic vowel at 16 kHz: `harmonics` partials of `f0` with a 1/h rolloff.
+pub(crate) fn vowel(f0: f32, harmonics: usize, amplitude: f32, len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / 16_000.0;
+            (1..=harmonics)
+                .map(|h| (2.0 * std::f32::consts::PI * f0 * h as f32 * t).sin() / h as f32)
+                .sum::<f32>()
+                * amplitude
+        })
+        .collect()
+}
+
+/// First-order low-pass of `input` (`pole` near 1 darkens the spectrum).
+pub(crate) fn low_pass(input: &[f32], pole: f32) -> Vec<f32> {
+    let mut state = 0.0;
+    input
+        .iter()
+        .map(|&s| {
+            state = state * pole + s * (1.0 - pole);
+            state
+        })
+        .collect()
+}
+
+/// Vowel at 16 kHz whose pitch moves linearly from `from_hz` to `to_hz`.
+pub(crate) fn glide(from_hz: f32, to_hz: f32, amplitude: f32, ms: usize) -> Vec<f32> {
+    let len = ms * 16;
+    let mut phase = 0.0f32;
+    (0..len)
+        .map(|i| {
+            let f0 = from_hz + (to_hz - from_hz) * i as f32 / len as f32;
+            phase += 2.0 * std::f32::consts::PI * f0 / 16_000.0;
+            (1..=6).map(|h| (phase * h as f32).sin() / h as f32).sum::<f32>() * amplitude
+        })
+        .collect()
+}
+
+pub(crate) fn engine(threshold: f64, silence_timeout_ms: u32, options: VadOptions) -> VadEngine {
+    VadEngine::with_options(threshold, silence_timeout_ms, options).unwrap()
+}
+
+/// Feeds `samples` as PCM16 in chunks of `chunk_ms`, then flushes.
+pub(crate) fn run_vad(vad: &mut VadEngine, samples: &[f32], chunk_ms: usize) -> Vec<VadStep> {
+    let bytes = pcm16(samples);
+    let mut steps: Vec<VadStep> = bytes.chunks(chunk_ms * 32).map(|c| vad.step(c)).collect();
+    steps.push(vad.finish());
+    steps
+}
+
+/// `(start_ms, end_ms)` of every closed segment.
+pub(crate) fn segments(steps: &[VadStep]) -> Vec<(f64, f64)> {
+    steps
+        .iter()
+        .filter(|s| s.kind == VadEventKind::SpeechEnd)
+        .map(|s| (s.segment_start_ms.unwrap(), s.segment_end_ms.unwrap()))
+        .collect()
+}
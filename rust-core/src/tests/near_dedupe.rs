@@ -0,0 +1,123 @@
+use crate::*;
+
+const MESSAGE: &str = "Hi team, the deploy for the billing service is scheduled for Thursday at 10am. \
+                       Please review the migration plan and flag any blockers before then.";
+
+fn detector(ttl_ms: u32, max_size: Option<u32>) -> RatchetNearDedupe {
+    RatchetNearDedupe::new(ttl_ms, Some(NearDedupeOptions { max_size, ..Default::default() }))
+}
+
+#[test]
+fn similarity_text_folds_presentation_differences() {
+    assert_eq!(similarity_text("  Ｈｅｌｌｏ,   WORLD!!\n"), "hello world");
+    assert_eq!(similarity_text("ﬁle — ①"), "file 1");
+    assert_eq!(similarity_text("?!..."), "");
+    assert_eq!(simhash("Hello, world"), simhash("hello world"));
+    assert_eq!(simhash(" \t!"), None);
+    assert!(simhash("ab").is_some(), "text shorter than a shingle still hashes");
+}
+
+#[test]
+fn re_deliveries_are_near_duplicates() {
+    let mut near = detector(60_000, None);
+    let first = near.check(MESSAGE.into(), Some(0.0), None);
+    assert!(!first.duplicate);
+    assert_eq!((first.distance, first.similarity), (64, 0.0));
+    assert_eq!(first.fingerprint.len(), 16);
+
+    for variant in [
+        MESSAGE.to_uppercase(),
+        format!("Fwd: {}", MESSAGE),
+        format!("RE:\r\n\t{}\r\n", MESSAGE.replace(' ', "\u{a0}")),
+        MESSAGE.replace(", ", " - ").replace('.', "!"),
+    ] {
+        let result = near.check(variant.clone(), Some(1_000.0), None);
+        assert!(result.duplicate, "distance {} for {:?}", result.distance, variant);
+        assert!(result.similarity >= 1.0 - 6.0 / 64.0);
+    }
+    // Duplicates are not recorded.
+    assert_eq!(near.size(), 1);
+}
+
+#[test]
+fn unrelated_messages_are_not_duplicates() {
+    let mut near = detector(60_000, None);
+    near.check(MESSAGE.into(), Some(0.0), None);
+    let other = near.check(
+        "Reminder: the quarterly offsite moved to the downtown office; lunch is provided for everyone attending.".into(),
+        Some(1.0),
+        None,
+    );
+    assert!(!other.duplicate);
+    assert!(other.distance > 16, "{}", other.distance);
+    assert_eq!(near.size(), 2);
+}
+
+#[test]
+fn fingerprints_expire_with_the_ttl() {
+    let mut near = detector(1_000, None);
+    near.check(MESSAGE.into(), Some(5_000.0), None);
+    // An entry with an older explicit timestamp sits behind a newer one.
+    near.check("completely different text about gardening tomatoes".into(), Some(0.0), None);
+    assert!(near.check(MESSAGE.into(), Some(6_000.0), None).duplicate);
+    assert!(!near.check("completely different text about gardening tomatoes".into(), Some(1_001.0), None).duplicate);
+    assert!(!near.check(MESSAGE.into(), Some(6_001.0), None).duplicate);
+}
+
+#[test]
+fn full_detectors_report_evictions() {
+    let mut near = detector(60_000, Some(2));
+    let texts = ["alpha bravo charlie delta", "echo foxtrot golf hotel", "india juliett kilo lima"];
+    let evicted: Vec<bool> = texts.iter().enumerate().map(|(i, text)| near.check(text.to_string(), Some(i as f64), None).evicted).collect();
+    assert_eq!(evicted, [false, false, true]);
+    assert_eq!(near.size(), 2);
+    assert!(!near.check(texts[0].into(), Some(10.0), None).duplicate);
+    assert!(near.check(texts[2].into(), Some(10.0), None).duplicate);
+
+    // Expired entries free their slots without counting as evictions.
+    assert!(!near.check("mike november oscar papa".into(), Some(70_000.0), None).evicted);
+    assert_eq!(near.size(), 1);
+    near.clear();
+    assert_eq!((near.size(), near.bands.len()), (0, 0));
+}
+
+#[test]
+fn short_and_empty_texts_are_skipped() {
+    let mut near = detector(60_000, None);
+    for text in ["ok", "yes!", "ok", "sounds good to", "..."] {
+        let result = near.check(text.into(), Some(0.0), None);
+        assert!(result.skipped && !result.duplicate && result.fingerprint.is_empty(), "{text}");
+    }
+    assert_eq!(near.size(), 0);
+
+    let mut chatty = RatchetNearDedupe::new(60_000, Some(NearDedupeOptions { min_words: Some(1), ..Default::default() }));
+    assert!(!chatty.check("ok".into(), Some(0.0), None).skipped);
+    assert!(chatty.check("OK!".into(), Some(1.0), None).duplicate);
+}
+
+#[test]
+fn scopes_do_not_match_each_other() {
+    let mut near = detector(60_000, None);
+    assert!(!near.check(MESSAGE.into(), Some(0.0), Some("whatsapp:alice".into())).duplicate);
+    assert!(!near.check(MESSAGE.into(), Some(1.0), Some("whatsapp:bob".into())).duplicate);
+    assert!(!near.check(MESSAGE.into(), Some(2.0), None).duplicate);
+    assert!(near.check(format!("Fwd: {}", MESSAGE), Some(3.0), Some("whatsapp:alice".into())).duplicate);
+    assert_eq!(near.size(), 3);
+}
+
+#[test]
+fn bands_find_every_fingerprint_within_the_distance() {
+    let strict = RatchetNearDedupe::new(60_000, Some(NearDedupeOptions { max_distance: Some(0), ..Default::default() }));
+    assert_eq!(strict.band_keys(0, u64::MAX).collect::<Vec<_>>(), [(0, 0, u64::MAX)]);
+
+    // Pigeonhole: flipping `max_distance` bits anywhere leaves a band intact.
+    let near = detector(60_000, None);
+    let base = 0x0123_4567_89ab_cdefu64;
+    let keys: Vec<_> = near.band_keys(0, base).collect();
+    assert_eq!(keys.len(), 7);
+    for flips in [[0, 9, 18, 27, 36, 45], [58, 59, 60, 61, 62, 63], [1, 10, 19, 28, 37, 55]] {
+        let other = flips.iter().fold(base, |fp, bit| fp ^ 1 << bit);
+        assert!(near.band_keys(0, other).any(|key| keys.contains(&key)), "{flips:?}");
+    }
+    assert_eq!(near.max_distance, 6);
+}
@@ -0,0 +1,111 @@
+use super::{engine, noise, run_vad, segments};
+use crate::*;
+
+fn adaptive() -> VadOptions {
+    VadOptions { adaptive: Some(true), ..Default::default() }
+}
+
+/// Noise at `level` RMS with a 440 Hz tone of `tone_rms` added while `loud`.
+fn scene(parts: &[(bool, usize)], level: f32, tone_rms: f32) -> Vec<f32> {
+    let mut seed = 1;
+    parts
+        .iter()
+        .flat_map(|&(loud, ms)| {
+            seed += 1;
+            let len = ms * 16;
+            let bed = noise(seed, level * 3f32.sqrt(), len);
+            let voice = super::tone(440.0, if loud { tone_rms * 2f32.sqrt() } else { 0.0 }, len, 16_000);
+            bed.into_iter().zip(voice).map(|(n, v)| n + v).collect::<Vec<f32>>()
+        })
+        .collect()
+}
+
+#[test]
+fn tracker_follows_the_windowed_minimum() {
+    let mut tracker = NoiseFloorTracker::new(10.0);
+    assert_eq!(tracker.floor, NOISE_FLOOR_MIN);
+    assert!(tracker.is_calibrating());
+
+    for _ in 0..10 {
+        tracker.update(400.0, 20.0);
+    }
+    assert!(!tracker.is_calibrating());
+    assert_eq!(tracker.floor, 400.0);
+
+    // Loud activity shorter than the window leaves the floor in place.
+    for _ in 0..100 {
+        tracker.update(5_000.0, 20.0);
+    }
+    assert!((tracker.floor - 400.0).abs() < 1.0, "{}", tracker.floor);
+
+    // Once the quiet audio leaves the window the floor rises to the new minimum.
+    for _ in 0..100 {
+        tracker.update(5_000.0, 20.0);
+    }
+    assert!(tracker.floor > 4_500.0, "{}", tracker.floor);
+
+    // Drops are followed at the smoothing rate (ten time constants here).
+    for _ in 0..100 {
+        tracker.update(200.0, 20.0);
+    }
+    assert!((tracker.floor - 200.0).abs() < 5.0, "{}", tracker.floor);
+}
+
+#[test]
+fn tracker_never_reports_below_the_minimum() {
+    let mut tracker = NoiseFloorTracker::new(500.0);
+    for _ in 0..200 {
+        tracker.update(0.0, 20.0);
+    }
+    assert_eq!(tracker.floor, NOISE_FLOOR_MIN);
+}
+
+#[test]
+fn thresholds_scale_with_the_floor() {
+    let mut vad = engine(900.0, 300, adaptive());
+    assert_eq!(vad.noise_floor(), 300.0);
+    let bytes = super::pcm16(&scene(&[(false, 1_000)], 400.0, 0.0));
+    bytes.chunks(640).for_each(|c| {
+        vad.step(c);
+    });
+    let floor = vad.noise_floor();
+    assert!((floor - 400.0).abs() < 60.0, "{floor}");
+    assert_eq!(vad.start_threshold(), floor * 3.0);
+    assert_eq!(vad.end_threshold(), floor * 2.0);
+
+    let fixed = engine(900.0, 300, VadOptions::default());
+    assert_eq!((fixed.noise_floor(), fixed.start_threshold(), fixed.end_threshold()), (0.0, 900.0, 900.0));
+}
+
+#[test]
+fn rejects_inverted_hysteresis() {
+    let ratios = |start, end| VadOptions { start_ratio: Some(start), end_ratio: Some(end), ..adaptive() };
+    assert!(VadEngine::with_options(900.0, 300, ratios(2.0, 3.0)).is_err());
+    assert!(VadEngine::with_options(900.0, 300, ratios(0.5, 0.5)).is_err());
+    assert!(VadEngine::with_options(900.0, 300, ratios(4.0, 1.5)).is_ok());
+}
+
+#[test]
+fn detects_speech_over_loud_background() {
+    // A fixed threshold tuned for a quiet room fires on the background alone.
+    let samples = scene(&[(false, 1_000), (true, 800), (false, 1_000), (true, 600), (false, 800)], 1_000.0, 5_000.0);
+    let mut fixed = engine(600.0, 300, VadOptions::default());
+    let found = segments(&run_vad(&mut fixed, &samples, 20));
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].0, 0.0);
+
+    let mut vad = engine(600.0, 300, adaptive());
+    let found = segments(&run_vad(&mut vad, &samples, 20));
+    assert_eq!(found.len(), 2, "{found:?}");
+    assert!((found[0].0 - 1_000.0).abs() <= 20.0 && (found[0].1 - 1_800.0).abs() <= 20.0, "{found:?}");
+    assert!((found[1].0 - 2_800.0).abs() <= 20.0 && (found[1].1 - 3_400.0).abs() <= 20.0, "{found:?}");
+}
+
+#[test]
+fn calibration_suppresses_early_decisions() {
+    // Speech from the first sample is learned as background, not reported.
+    let samples = scene(&[(true, 150), (false, 600)], 100.0, 5_000.0);
+    let mut vad = engine(600.0, 300, adaptive());
+    let steps = run_vad(&mut vad, &samples, 20);
+    assert!(steps.iter().all(|s| s.kind == VadEventKind::Silent));
+}
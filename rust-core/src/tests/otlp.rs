@@ -0,0 +1,182 @@
+use crate::*;
+
+/// One decoded protobuf field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+}
+
+fn varint(buf: &[u8], pos: &mut usize) -> u64 {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = buf[*pos];
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte < 0x80 {
+            break;
+        }
+    }
+    value
+}
+
+/// Splits a message into `(field number, value)` pairs, in wire order.
+fn decode(buf: &[u8]) -> Vec<(u32, Field<'_>)> {
+    let mut pos = 0;
+    let mut fields = Vec::new();
+    while pos < buf.len() {
+        let tag = varint(buf, &mut pos);
+        let value = match tag & 7 {
+            0 => Field::Varint(varint(buf, &mut pos)),
+            1 => {
+                pos += 8;
+                Field::Fixed64(u64::from_le_bytes(buf[pos - 8..pos].try_into().unwrap()))
+            }
+            2 => {
+                let len = varint(buf, &mut pos) as usize;
+                pos += len;
+                Field::Bytes(&buf[pos - len..pos])
+            }
+            wire => panic!("unexpected wire type {}", wire),
+        };
+        fields.push(((tag >> 3) as u32, value));
+    }
+    fields
+}
+
+fn all(buf: &[u8], field: u32) -> Vec<Field<'_>> {
+    decode(buf).into_iter().filter(|(f, _)| *f == field).map(|(_, v)| v).collect()
+}
+
+fn bytes(buf: &[u8], field: u32) -> &[u8] {
+    match all(buf, field).first() {
+        Some(Field::Bytes(b)) => b,
+        other => panic!("field {} is {:?}", field, other),
+    }
+}
+
+fn text(buf: &[u8], field: u32) -> &str {
+    std::str::from_utf8(bytes(buf, field)).unwrap()
+}
+
+fn number(buf: &[u8], field: u32) -> u64 {
+    match all(buf, field).first() {
+        Some(Field::Varint(v) | Field::Fixed64(v)) => *v,
+        other => panic!("field {} is {:?}", field, other),
+    }
+}
+
+fn packed(buf: &[u8], field: u32) -> Vec<u64> {
+    bytes(buf, field).chunks(8).map(|c| u64::from_le_bytes(c.try_into().unwrap())).collect()
+}
+
+/// `KeyValue` attributes as `(key, string value)` pairs.
+fn attributes(buf: &[u8], field: u32) -> Vec<(&str, &str)> {
+    all(buf, field)
+        .into_iter()
+        .map(|kv| match kv {
+            Field::Bytes(kv) => (text(kv, 1), text(bytes(kv, 2), 1)),
+            other => panic!("attribute is {:?}", other),
+        })
+        .collect()
+}
+
+/// Returns the `Metric` messages of a single-resource, single-scope request.
+fn metrics(request: &[u8]) -> Vec<&[u8]> {
+    let scope_metrics = bytes(bytes(request, 1), 2);
+    all(scope_metrics, 2).into_iter().map(|m| if let Field::Bytes(m) = m { m } else { unreachable!() }).collect()
+}
+
+const NOW_MS: f64 = 1_767_225_600_000.0;
+
+#[test]
+fn proto_writer_encodes_varints_and_fields() {
+    let mut w = ProtoWriter::default();
+    w.uint(1, 300);
+    w.string(2, "hi");
+    w.double(3, 1.5);
+    assert_eq!(&w.buf[..6], [0x08, 0xac, 0x02, 0x12, 0x02, b'h']);
+    assert_eq!(
+        decode(&w.buf),
+        [(1, Field::Varint(300)), (2, Field::Bytes(b"hi")), (3, Field::Fixed64(1.5f64.to_bits()))]
+    );
+}
+
+#[test]
+fn trace_ids_must_be_exact_hex() {
+    assert_eq!(parse_trace_id::<8>("00f067aa0ba902b7", "spanId").unwrap(), [0x00, 0xf0, 0x67, 0xaa, 0x0b, 0xa9, 0x02, 0xb7]);
+    for bad in ["00f067aa0ba902b", "00f067aa0ba902b7ff", "00f067aa0ba902zz", "+0f067aa0ba902b7", "ééééééé0"] {
+        assert_eq!(parse_trace_id::<8>(bad, "spanId").unwrap_err().status, napi::Status::InvalidArg, "{}", bad);
+    }
+}
+
+#[test]
+fn request_carries_resource_and_scope() {
+    let mut engine = MetricsEngine::default();
+    engine.record_tokens("m".into(), 1, None, None).unwrap();
+    let request = engine.encode_otlp(Some("gateway"), NOW_MS);
+    let resource_metrics = bytes(&request, 1);
+    assert_eq!(attributes(bytes(resource_metrics, 1), 1), [("service.name", "gateway")]);
+    let scope = bytes(bytes(resource_metrics, 2), 1);
+    assert_eq!((text(scope, 1), text(scope, 2)), ("ratchet", env!("CARGO_PKG_VERSION")));
+
+    let default_name = engine.encode_otlp(None, NOW_MS);
+    assert_eq!(attributes(bytes(bytes(&default_name, 1), 1), 1), [("service.name", "ratchet")]);
+}
+
+#[test]
+fn counters_and_gauges_encode_number_points() {
+    let mut engine = MetricsEngine { start_time_ms: NOW_MS - 1_000.0, ..Default::default() };
+    engine.record_tokens("m".into(), 42, None, None).unwrap();
+    engine.set_gauge("queue".into(), 2.5, None).unwrap();
+    let request = engine.encode_otlp(None, NOW_MS);
+    // Metrics without series are skipped.
+    let names: Vec<&str> = metrics(&request).iter().map(|m| text(m, 1)).collect();
+    assert_eq!(names, [TOKENS_METRIC, "queue"]);
+
+    let tokens = metrics(&request)[0];
+    assert_eq!(text(tokens, 3), "{token}");
+    let sum = bytes(tokens, 7);
+    assert_eq!((number(sum, 2), number(sum, 3)), (OTLP_CUMULATIVE, 1));
+    let point = bytes(sum, 1);
+    assert_eq!(attributes(point, 7), [("model", "m")]);
+    assert_eq!(number(point, 2), unix_nanos(NOW_MS - 1_000.0));
+    assert_eq!(number(point, 3), unix_nanos(NOW_MS));
+    assert_eq!(f64::from_bits(number(point, 4)), 42.0);
+
+    let gauge_point = bytes(bytes(metrics(&request)[1], 5), 1);
+    assert_eq!(f64::from_bits(number(gauge_point, 4)), 2.5);
+}
+
+#[test]
+fn histograms_encode_buckets_and_exemplars() {
+    let mut engine = MetricsEngine::default();
+    let trace = TraceContext { trace_id: "4bf92f3577b34da6a3ce929d0e0e4736".into(), span_id: Some("00f067aa0ba902b7".into()) };
+    engine.record_latency(7.0, None, Some(NOW_MS - 5.0), Some(trace)).unwrap();
+    engine.record_latency(20_000.0, None, Some(NOW_MS), None).unwrap();
+    let request = engine.encode_otlp(None, NOW_MS);
+    let latency = metrics(&request)[0];
+    assert_eq!((text(latency, 1), text(latency, 3)), (LATENCY_METRIC, "ms"));
+
+    let histogram = bytes(latency, 9);
+    assert_eq!(number(histogram, 2), OTLP_CUMULATIVE);
+    let point = bytes(histogram, 1);
+    assert_eq!(number(point, 4), 2);
+    assert_eq!(f64::from_bits(number(point, 5)), 20_007.0);
+    let bounds: Vec<f64> = packed(point, 7).into_iter().map(f64::from_bits).collect();
+    assert_eq!(bounds, DEFAULT_LATENCY_BUCKETS);
+    let counts = packed(point, 6);
+    assert_eq!(counts.len(), bounds.len() + 1);
+    assert_eq!((counts[1], counts[bounds.len()], counts.iter().sum::<u64>()), (1, 1, 2));
+
+    let exemplars = all(point, 8);
+    assert_eq!(exemplars.len(), 2);
+    let Field::Bytes(traced) = exemplars[0] else { unreachable!() };
+    assert_eq!(number(traced, 2), unix_nanos(NOW_MS - 5.0));
+    assert_eq!(f64::from_bits(number(traced, 3)), 7.0);
+    assert_eq!(bytes(traced, 4), [0x00, 0xf0, 0x67, 0xaa, 0x0b, 0xa9, 0x02, 0xb7]);
+    assert_eq!(bytes(traced, 5)[..4], [0x4b, 0xf9, 0x2f, 0x35]);
+    let Field::Bytes(untraced) = exemplars[1] else { unreachable!() };
+    assert!(all(untraced, 4).is_empty() && all(untraced, 5).is_empty());
+}
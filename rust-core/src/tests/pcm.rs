@@ -0,0 +1,89 @@
+use super::{pcm16, rms, tone};
+use crate::*;
+
+fn format(encoding: &str, channels: u32, sample_rate: u32) -> AudioFormat {
+    AudioFormat { encoding: encoding.to_string(), channels, sample_rate }
+}
+
+fn decoder(encoding: &str, channels: u32, sample_rate: u32) -> PcmDecoder {
+    PcmDecoder::new(&format(encoding, channels, sample_rate), VAD_SAMPLE_RATE).unwrap()
+}
+
+#[test]
+fn decodes_every_encoding_to_the_i16_range() {
+    assert_eq!(decoder("s16le", 1, 16_000).decode(&pcm16(&[1000.0, -1000.0])), vec![1000.0, -1000.0]);
+    assert_eq!(decoder("u8", 1, 16_000).decode(&[128, 0]), vec![0.0, -32_768.0]);
+    assert_eq!(decoder("s24le", 1, 16_000).decode(&[0x00, 0x00, 0x80]), vec![-32_768.0]);
+    assert_eq!(decoder("s32le", 1, 16_000).decode(&i32::MIN.to_le_bytes()), vec![-32_768.0]);
+
+    let floats: Vec<u8> = [0.5f32, 2.0, f32::NAN].iter().flat_map(|v| v.to_le_bytes()).collect();
+    assert_eq!(decoder("f32le", 1, 16_000).decode(&floats), vec![16_384.0, 32_768.0, 0.0]);
+
+    // G.711 silence and full-scale codes.
+    assert_eq!(decoder("mulaw", 1, 16_000).decode(&[0xFF, 0x00]), vec![0.0, -32_124.0]);
+    assert_eq!(decoder("alaw", 1, 16_000).decode(&[0xD5, 0x55]), vec![8.0, -8.0]);
+}
+
+#[test]
+fn accepts_encoding_aliases() {
+    for name in ["pcm16", "I16", "float32", "ulaw", "PCMA"] {
+        assert!(SampleEncoding::parse(name).is_some(), "{name}");
+    }
+    assert!(SampleEncoding::parse("opus").is_none());
+}
+
+#[test]
+fn rejects_invalid_formats() {
+    assert!(PcmDecoder::new(&format("opus", 1, 16_000), VAD_SAMPLE_RATE).is_err());
+    assert!(PcmDecoder::new(&format("s16le", 0, 16_000), VAD_SAMPLE_RATE).is_err());
+    assert!(PcmDecoder::new(&format("s16le", 33, 16_000), VAD_SAMPLE_RATE).is_err());
+    assert!(PcmDecoder::new(&format("s16le", 1, 999), VAD_SAMPLE_RATE).is_err());
+}
+
+#[test]
+fn down_mixes_interleaved_channels() {
+    let stereo = pcm16(&[1000.0, -1000.0, 2000.0, 2000.0]);
+    assert_eq!(decoder("s16le", 2, 16_000).decode(&stereo), vec![0.0, 2000.0]);
+}
+
+#[test]
+fn carries_partial_frames_across_chunks() {
+    let mut pcm = decoder("s16le", 2, 16_000);
+    let bytes = pcm16(&[100.0, 300.0, -500.0, -700.0]);
+    assert!(pcm.decode(&bytes[..3]).is_empty());
+    assert_eq!(pcm.decode(&bytes[3..5]), vec![200.0]);
+    assert_eq!(pcm.decode(&bytes[5..]), vec![-600.0]);
+
+    pcm.decode(&bytes[..1]);
+    pcm.reset();
+    assert_eq!(pcm.decode(&bytes[..4]), vec![200.0]);
+}
+
+#[test]
+fn resamples_to_the_analysis_rate_seamlessly() {
+    for rate in [8_000, 44_100, 48_000] {
+        let input = tone(440.0, 10_000.0, rate as usize, rate);
+        let bytes = pcm16(&input);
+        let mut pcm = decoder("s16le", 1, rate);
+        // Odd chunk sizes split frames and resampler steps.
+        let out: Vec<f32> = bytes.chunks(333).flat_map(|c| pcm.decode(c)).collect();
+        assert!((out.len() as i64 - VAD_SAMPLE_RATE as i64).abs() <= 2, "{rate}: {} samples", out.len());
+        let level = rms(&out[100..out.len() - 100]);
+        assert!((level - 10_000.0 / 2f32.sqrt()).abs() < 350.0, "{rate}: rms {level}");
+    }
+}
+
+#[test]
+fn filters_out_tones_above_the_output_nyquist() {
+    for (rate, freq) in [(48_000, 12_000.0), (44_100, 9_000.0), (22_050, 10_000.0)] {
+        let mut pcm = decoder("s16le", 1, rate);
+        let out = pcm.decode(&pcm16(&tone(freq, 10_000.0, rate as usize, rate)));
+        let level = rms(&out[200..]);
+        assert!(level < 100.0, "{rate} Hz input, {freq} Hz tone: rms {level}");
+    }
+    // The speech band passes at full level.
+    let mut pcm = decoder("s16le", 1, 48_000);
+    let out = pcm.decode(&pcm16(&tone(3_000.0, 10_000.0, 48_000, 48_000)));
+    let level = rms(&out[200..]);
+    assert!((level - 10_000.0 / 2f32.sqrt()).abs() < 150.0, "rms {level}");
+}
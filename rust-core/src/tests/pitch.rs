@@ -0,0 +1,61 @@
+use super::{noise, tone, vowel};
+use crate::*;
+
+const SPEAKING_RANGE: [f32; 12] = [70.0, 80.0, 95.0, 110.0, 130.0, 150.0, 180.0, 220.0, 260.0, 300.0, 350.0, 400.0];
+
+fn assert_pitch(frame: &[f32], expected: f32, label: &str) {
+    let found = estimate_pitch(frame, VAD_SAMPLE_RATE).unwrap_or_else(|| panic!("{label}: unvoiced"));
+    let error = (found / expected as f64 - 1.0).abs();
+    assert!(error < 0.01, "{label}: expected {expected} Hz, got {found:.1} Hz");
+}
+
+#[test]
+fn tracks_pure_tones_across_the_speaking_range() {
+    for f0 in SPEAKING_RANGE {
+        assert_pitch(&tone(f0, 8_000.0, 640, VAD_SAMPLE_RATE), f0, &format!("{f0} Hz tone"));
+    }
+}
+
+#[test]
+fn does_not_lock_onto_sub_harmonics() {
+    for f0 in SPEAKING_RANGE {
+        for harmonics in [3, 8, 12] {
+            let frame = vowel(f0, harmonics, 3_000.0, 640);
+            assert_pitch(&frame, f0, &format!("{f0} Hz x{harmonics}"));
+        }
+    }
+}
+
+#[test]
+fn tolerates_moderate_noise() {
+    for f0 in [90.0, 180.0, 350.0] {
+        let frame: Vec<f32> = vowel(f0, 8, 3_000.0, 640).iter().zip(noise(2, 800.0, 640)).map(|(v, n)| v + n).collect();
+        assert_pitch(&frame, f0, &format!("{f0} Hz in noise"));
+    }
+}
+
+#[test]
+fn rejects_unvoiced_frames() {
+    assert_eq!(estimate_pitch(&noise(1, 8_000.0, 640), VAD_SAMPLE_RATE), None);
+    assert_eq!(estimate_pitch(&[0.0; 640], VAD_SAMPLE_RATE), None);
+    // Shorter than two periods of the lowest pitch.
+    assert_eq!(estimate_pitch(&tone(200.0, 8_000.0, 400, VAD_SAMPLE_RATE), VAD_SAMPLE_RATE), None);
+}
+
+#[test]
+fn tracker_assembles_frames_from_small_chunks() {
+    let mut tracker = PitchTracker::new();
+    let audio = vowel(350.0, 8, 3_000.0, 3_200);
+    let estimates: Vec<Option<f64>> = audio.chunks(160).map(|c| tracker.push(c)).collect();
+    assert!(estimates[..2].iter().all(Option::is_none));
+    assert!(estimates[3..].iter().all(|p| (p.unwrap() - 350.0).abs() < 3.5));
+
+    tracker.reset();
+    assert_eq!(tracker.push(&audio[..160]), None);
+}
+
+#[test]
+fn semitones_are_logarithmic() {
+    assert!((semitones(100.0, 200.0) - 12.0).abs() < 1e-9);
+    assert!((semitones(220.0, 110.0) + 12.0).abs() < 1e-9);
+}
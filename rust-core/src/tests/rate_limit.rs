@@ -0,0 +1,99 @@
+use crate::*;
+
+fn limiter(algorithm: RateLimitAlgorithm, limit: u32, window_ms: u32, max_keys: Option<u32>) -> RatchetRateLimiter {
+    RatchetRateLimiter::new(RateLimitOptions { algorithm: Some(algorithm), limit, window_ms, max_keys }).unwrap()
+}
+
+fn check(limiter: &mut RatchetRateLimiter, key: &str, cost: u32, at: f64) -> RateLimitDecision {
+    limiter.check(key.into(), Some(cost), Some(at)).unwrap()
+}
+
+#[test]
+fn invalid_options_and_costs_are_rejected() {
+    let options = |algorithm, limit, window_ms| RateLimitOptions { algorithm: Some(algorithm), limit, window_ms, max_keys: None };
+    assert!(RatchetRateLimiter::new(options(RateLimitAlgorithm::TokenBucket, 0, 1)).is_err());
+    assert!(RatchetRateLimiter::new(options(RateLimitAlgorithm::SlidingWindow, 1, 0)).is_err());
+
+    let mut bucket = limiter(RateLimitAlgorithm::TokenBucket, 5, 1_000, None);
+    for cost in [0, 6] {
+        let err = bucket.check("a".into(), Some(cost), Some(0.0)).err().unwrap();
+        assert_eq!(err.status, napi::Status::InvalidArg);
+    }
+    assert_eq!(bucket.size(), 0);
+}
+
+#[test]
+fn token_bucket_refills_continuously() {
+    let mut bucket = limiter(RateLimitAlgorithm::TokenBucket, 10, 1_000, None);
+    let decision = check(&mut bucket, "a", 10, 0.0);
+    assert!(decision.allowed && decision.remaining == 0.0);
+
+    let denied = check(&mut bucket, "a", 3, 100.0);
+    assert!(!denied.allowed);
+    assert_eq!((denied.remaining, denied.retry_after_ms), (1.0, 200.0));
+    assert!(check(&mut bucket, "a", 3, 300.0).allowed);
+
+    // Late timestamps neither refill nor rewind the bucket.
+    assert!(!check(&mut bucket, "a", 1, 0.0).allowed);
+    assert!(check(&mut bucket, "b", 10, 0.0).allowed);
+}
+
+#[test]
+fn sliding_window_waits_for_the_oldest_requests() {
+    let mut window = limiter(RateLimitAlgorithm::SlidingWindow, 3, 1_000, None);
+    for at in [0.0, 200.0, 400.0] {
+        assert!(check(&mut window, "a", 1, at).allowed);
+    }
+    let denied = check(&mut window, "a", 2, 500.0);
+    assert!(!denied.allowed);
+    // Two slots free up once the request at 200 ms leaves the window.
+    assert_eq!((denied.remaining, denied.retry_after_ms), (0.0, 700.0));
+    assert!(!check(&mut window, "a", 2, 1_100.0).allowed);
+    let allowed = check(&mut window, "a", 2, 1_200.0);
+    assert!(allowed.allowed && allowed.remaining == 0.0);
+}
+
+#[test]
+fn eviction_drops_the_least_recently_checked_key() {
+    let mut limits = limiter(RateLimitAlgorithm::TokenBucket, 2, 60_000, Some(2));
+    check(&mut limits, "a", 2, 0.0);
+    check(&mut limits, "b", 2, 0.0);
+    // Touching `a` makes `b` the eviction candidate.
+    assert!(!check(&mut limits, "a", 1, 1.0).allowed);
+    check(&mut limits, "c", 1, 2.0);
+    assert_eq!(limits.size(), 2);
+    assert!(limits.keys.contains_key("a") && !limits.keys.contains_key("b"));
+    assert!(!check(&mut limits, "a", 1, 3.0).allowed, "a kept its exhausted bucket");
+    assert!(check(&mut limits, "b", 2, 4.0).allowed, "b starts over");
+    assert_eq!(limits.recency.len(), limits.keys.len());
+}
+
+#[test]
+fn eviction_keeps_the_index_consistent_under_churn() {
+    let mut limits = limiter(RateLimitAlgorithm::SlidingWindow, 5, 1_000, Some(100));
+    for i in 0..10_000u32 {
+        let key = format!("k{}", i % 300);
+        check(&mut limits, &key, 1, i as f64);
+        if i % 7 == 0 {
+            limits.reset(format!("k{}", i % 50));
+        }
+    }
+    assert!(limits.size() <= 100);
+    assert_eq!(limits.recency.len(), limits.keys.len());
+    assert!(limits.recency.values().all(|key| limits.keys.contains_key(key)));
+}
+
+#[test]
+fn reset_and_clear_restore_the_allowance() {
+    let mut limits = limiter(RateLimitAlgorithm::SlidingWindow, 1, 60_000, None);
+    check(&mut limits, "a", 1, 0.0);
+    check(&mut limits, "b", 1, 0.0);
+    limits.reset("a".into());
+    limits.reset("missing".into());
+    assert_eq!(limits.size(), 1);
+    assert!(check(&mut limits, "a", 1, 1.0).allowed);
+    assert!(!check(&mut limits, "b", 1, 1.0).allowed);
+    limits.clear();
+    assert!(limits.recency.is_empty());
+    assert!(check(&mut limits, "b", 1, 2.0).allowed);
+}
@@ -0,0 +1,113 @@
+use crate::*;
+
+fn descriptor(name: &str, kind: MetricKind, buckets: Option<Vec<f64>>, max_series: Option<u32>) -> MetricDescriptor {
+    MetricDescriptor { name: name.into(), kind, help: Some("help".into()), buckets, max_series }
+}
+
+fn labels(user: &str) -> Option<HashMap<String, String>> {
+    Some(HashMap::from([("user".to_string(), user.to_string())]))
+}
+
+fn round_trip(engine: &MetricsEngine) -> MetricsEngine {
+    MetricsEngine::decode_snapshot(&engine.encode_snapshot()).unwrap()
+}
+
+#[test]
+fn snapshots_restore_the_full_state() {
+    let mut engine = MetricsEngine::new(Some(5));
+    engine
+        .set_pricing(vec![ModelPricing {
+            model: "m".into(),
+            provider: Some("acme".into()),
+            input_per_million: 1.0,
+            output_per_million: 2.0,
+            cached_per_million: None,
+        }])
+        .unwrap();
+    engine.set_budget(CostBudget { daily_usd: Some(10.0), monthly_usd: None }).unwrap();
+    engine.record_usage("m".into(), 1_000_000, 1_000_000, None, None).unwrap();
+    for ms in [10.0, 20.0, 30.0, 40.0, 50.0, 60.0, 70.0] {
+        engine.record_latency(ms, None, None, None).unwrap();
+    }
+    engine.set_gauge("queue".into(), -3.5, labels("a")).unwrap();
+
+    let mut restored = round_trip(&engine);
+    assert_eq!(restored.render_prometheus(Some(true)), engine.render_prometheus(Some(true)));
+    let (before, after) = (engine.summarize(), restored.summarize());
+    assert_eq!((after.total_tokens, after.latency_samples, after.p50_latency_ms), (before.total_tokens, 5, before.p50_latency_ms));
+    assert_eq!((restored.latency_window, restored.latency_idx), (engine.latency_window, engine.latency_idx));
+    assert_eq!(restored.start_time_ms, engine.start_time_ms);
+    let report = restored.cost_report(None).unwrap();
+    assert_eq!((report.total_usd, report.daily_usd, report.by_provider[0].name.as_str()), (3.0, 3.0, "acme"));
+    let now = unix_time_ms();
+    assert_eq!(
+        restored.history(HistoryResolution::Minute, now - 60_000.0, now + 60_000.0).iter().map(|p| p.tokens).sum::<f64>(),
+        2_000_000.0
+    );
+}
+
+#[test]
+fn corrupt_snapshots_are_rejected() {
+    let mut engine = MetricsEngine::default();
+    engine.record_tokens("m".into(), 1, None, None).unwrap();
+    let blob = engine.encode_snapshot();
+
+    let reason = |data: &[u8]| MetricsEngine::decode_snapshot(data).err().unwrap().reason;
+    assert!(reason(b"RTMS").contains("not a metrics snapshot"));
+    assert!(reason(&blob[..blob.len() - 1]).contains("checksum"));
+    let mut flipped = blob.clone();
+    flipped[20] ^= 1;
+    assert!(reason(&flipped).contains("checksum"));
+
+    // A newer version is refused even with a valid checksum.
+    let mut future = blob[..blob.len() - 8].to_vec();
+    future[4..6].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+    let checksum = fnv1a64(&future);
+    future.extend_from_slice(&checksum.to_le_bytes());
+    assert!(reason(&future).contains("version"));
+}
+
+#[test]
+fn bucket_limit_matches_what_snapshots_accept() {
+    let mut engine = MetricsEngine::default();
+    let too_many: Vec<f64> = (0..=MAX_SNAPSHOT_BUCKETS).map(|i| i as f64).collect();
+    let err = engine.define_metric(descriptor("wide", MetricKind::Histogram, Some(too_many.clone()), None)).unwrap_err();
+    assert_eq!(err.status, napi::Status::InvalidArg);
+
+    // Duplicates and non-finite bounds do not count toward the limit.
+    let mut at_limit = too_many[..MAX_SNAPSHOT_BUCKETS].to_vec();
+    at_limit.extend([0.0, f64::NAN, f64::INFINITY]);
+    engine.define_metric(descriptor("wide", MetricKind::Histogram, Some(at_limit), None)).unwrap();
+    engine.observe_histogram("wide".into(), 3.0, None).unwrap();
+    assert_eq!(round_trip(&engine).metrics["wide"].buckets.len(), MAX_SNAPSHOT_BUCKETS);
+}
+
+#[test]
+fn lowering_max_series_evicts_the_oldest_series() {
+    let mut engine = MetricsEngine::default();
+    engine.define_metric(descriptor("per_user", MetricKind::Counter, None, Some(10))).unwrap();
+    for user in ["a", "b", "c", "d"] {
+        engine.increment_counter("per_user".into(), None, labels(user)).unwrap();
+    }
+    engine.define_metric(descriptor("per_user", MetricKind::Counter, None, Some(2))).unwrap();
+    let metric = &engine.metrics["per_user"];
+    let users: Vec<&str> = metric.series.keys().map(|l| l[0].1.as_str()).collect();
+    assert_eq!(users, ["c", "d"]);
+    assert_eq!(metric.dropped_series, 2);
+
+    // The snapshot now stays within the limit restore enforces.
+    let restored = round_trip(&engine);
+    assert_eq!(restored.metrics["per_user"].series.len(), 2);
+    assert_eq!(restored.summarize().dropped_series, 2.0);
+}
+
+#[test]
+fn token_totals_round_trip() {
+    let mut engine = MetricsEngine::default();
+    for i in 0..30 {
+        engine.record_usage(format!("m{}", i), 10, 20, Some(5), None).unwrap();
+    }
+    let restored = round_trip(&engine);
+    assert_eq!(restored.token_totals, engine.token_totals);
+    assert_eq!(restored.summarize().total_tokens, 30.0 * 35.0);
+}
@@ -0,0 +1,71 @@
+use super::{low_pass, noise, rms, tone, vowel};
+use crate::*;
+
+/// Share of 20 ms chunks judged voice-like once the analyzer has a full frame.
+fn voiced_share(samples: &[f32]) -> f64 {
+    let mut analyzer = SpectralAnalyzer::new(VAD_SAMPLE_RATE);
+    let chunks: Vec<bool> = samples.chunks(320).map(|c| analyzer.analyze(c).is_voice_like()).collect();
+    let settled = &chunks[2..];
+    settled.iter().filter(|&&v| v).count() as f64 / settled.len() as f64
+}
+
+fn with_noise(signal: &[f32], snr_db: f32, seed: u64) -> Vec<f32> {
+    // Uniform noise of amplitude a has RMS a / sqrt(3).
+    let amplitude = rms(signal) / 10f32.powf(snr_db / 20.0) * 3f32.sqrt();
+    signal.iter().zip(noise(seed, amplitude, signal.len())).map(|(s, n)| s + n).collect()
+}
+
+#[test]
+fn fft_peaks_at_the_tone_bin() {
+    let mut re = tone(1_000.0, 1.0, SPECTRAL_FRAME, VAD_SAMPLE_RATE);
+    let mut im = vec![0.0; SPECTRAL_FRAME];
+    fft_in_place(&mut re, &mut im);
+    let power: Vec<f32> = re.iter().zip(&im).take(SPECTRAL_FRAME / 2).map(|(r, i)| r * r + i * i).collect();
+    let peak = power.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0;
+    assert_eq!(peak, 32); // 1000 Hz / (16000 / 512)
+}
+
+#[test]
+fn accepts_vowels_across_the_speaking_range() {
+    for f0 in [85.0, 110.0, 150.0, 220.0, 300.0] {
+        for harmonics in [6, 12, 20] {
+            let share = voiced_share(&vowel(f0, harmonics, 4_000.0, 16_000));
+            assert_eq!(share, 1.0, "{f0} Hz x{harmonics}");
+        }
+    }
+}
+
+#[test]
+fn accepts_vowels_in_moderate_noise() {
+    for f0 in [85.0, 150.0, 220.0] {
+        let noisy = with_noise(&vowel(f0, 12, 4_000.0, 16_000), 10.0, 5);
+        assert!(voiced_share(&noisy) > 0.95, "{f0} Hz at 10 dB SNR");
+    }
+}
+
+#[test]
+fn rejects_noise_and_hum() {
+    let white = noise(7, 8_000.0, 16_000);
+    let hiss: Vec<f32> = noise(11, 8_000.0, 16_001).windows(2).map(|p| p[1] - p[0]).collect();
+    let mut brown_state = 0.0;
+    let brown: Vec<f32> = noise(9, 800.0, 16_000)
+        .iter()
+        .map(|s| {
+            brown_state = brown_state * 0.995 + s;
+            brown_state
+        })
+        .collect();
+    // Rumble this dark drifts into the low-vowel band ratio now and then;
+    // isolated chunks are absorbed by the speech-start debounce.
+    let fixtures = [
+        ("white", white.clone(), 0.05),
+        ("hiss", hiss, 0.05),
+        ("brown", brown, 0.2),
+        ("colored", low_pass(&white, 0.7), 0.05),
+        ("hum", tone(60.0, 8_000.0, 16_000, VAD_SAMPLE_RATE), 0.05),
+    ];
+    for (name, samples, tolerance) in fixtures {
+        let share = voiced_share(&samples);
+        assert!(share < tolerance, "{name}: {share}");
+    }
+}
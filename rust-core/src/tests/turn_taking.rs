@@ -0,0 +1,102 @@
+use super::{glide, pcm16};
+use crate::*;
+
+/// Feeds `phrase` then `silence_ms` of silence in 40 ms chunks; returns the last prediction.
+fn after_pause(engine: &mut TurnTakingEngine, phrase: &[f32], silence_ms: usize, hint: Option<&str>) -> TurnPrediction {
+    let mut audio = phrase.to_vec();
+    audio.extend(std::iter::repeat_n(0.0, 16 * silence_ms));
+    let mut last = None;
+    for (i, chunk) in pcm16(&audio).chunks(1_280).enumerate() {
+        last = Some(engine.predict(chunk, (i == 0).then(|| hint.unwrap_or("").to_string())));
+    }
+    last.unwrap()
+}
+
+fn engine() -> TurnTakingEngine {
+    TurnTakingEngine::new(None).unwrap()
+}
+
+#[test]
+fn rejects_non_finite_options() {
+    for (energy, threshold) in [(f64::NAN, 0.7), (f64::INFINITY, 0.7), (-1.0, 0.7), (500.0, f64::NAN), (500.0, f64::NEG_INFINITY)] {
+        let options = TurnTakingOptions { min_active_energy: Some(energy), decision_threshold: Some(threshold), ..Default::default() };
+        assert!(TurnTakingEngine::new(Some(options)).is_err(), "{energy} {threshold}");
+    }
+}
+
+#[test]
+fn transcript_hints_score_sentence_endings() {
+    assert_eq!(transcript_hint_score(""), 0.0);
+    assert_eq!(transcript_hint_score("what time is it?"), 1.5);
+    assert_eq!(transcript_hint_score("that's all. "), 1.0);
+    assert_eq!(transcript_hint_score("first,"), -1.5);
+    assert_eq!(transcript_hint_score("I went there and"), -1.5);
+    assert_eq!(transcript_hint_score("fui lá e depois ENTÃO"), -1.5);
+    assert_eq!(transcript_hint_score("I went there"), 0.0);
+}
+
+#[test]
+fn speech_and_leading_silence_never_end_the_turn() {
+    let mut turn = engine();
+    let idle = after_pause(&mut turn, &[], 2_000, None);
+    assert_eq!((idle.probability, idle.end_of_turn), (0.0, false));
+
+    let speaking = turn.predict(&pcm16(&glide(150.0, 150.0, 6_000.0, 40)), Some("done.".into()));
+    assert!(speaking.is_speaking);
+    assert_eq!(speaking.probability, 0.0);
+}
+
+#[test]
+fn probability_grows_with_silence_until_the_hard_limit() {
+    let phrase = glide(160.0, 160.0, 6_000.0, 1_000);
+    let probabilities: Vec<f64> = [0, 200, 400, 800, 1_200, 1_520]
+        .iter()
+        .map(|&ms| after_pause(&mut engine(), &phrase, ms, None).probability)
+        .collect();
+    assert!(probabilities.windows(2).all(|w| w[0] <= w[1]), "{probabilities:?}");
+    assert!(probabilities[1] < 0.5);
+    assert_eq!(probabilities[5], 1.0);
+
+    let done = after_pause(&mut engine(), &phrase, 1_520, None);
+    assert!(done.end_of_turn && !done.is_speaking);
+    assert!((done.silence_ms - 1_520.0).abs() < 1.0);
+}
+
+#[test]
+fn falling_pitch_and_hints_shift_the_curve() {
+    let falling = after_pause(&mut engine(), &glide(200.0, 110.0, 6_000.0, 1_000), 400, None);
+    let rising = after_pause(&mut engine(), &glide(110.0, 200.0, 6_000.0, 1_000), 400, None);
+    assert!(falling.pitch_drop_semitones > 3.0, "{}", falling.pitch_drop_semitones);
+    assert!(rising.pitch_drop_semitones <= 0.0);
+    assert!(falling.probability > rising.probability);
+
+    let phrase = glide(160.0, 160.0, 6_000.0, 1_000);
+    let question = after_pause(&mut engine(), &phrase, 400, Some("is that right?"));
+    let connective = after_pause(&mut engine(), &phrase, 400, Some("and then"));
+    assert_eq!((question.hint_score, connective.hint_score), (1.5, -1.5));
+    assert!(question.probability > connective.probability);
+}
+
+#[test]
+fn energy_decay_tracks_a_fading_phrase_end() {
+    let mut phrase = glide(160.0, 160.0, 8_000.0, 1_000);
+    phrase.extend(glide(160.0, 160.0, 1_500.0, 120));
+    let fading = after_pause(&mut engine(), &phrase, 200, None);
+    let steady = after_pause(&mut engine(), &glide(160.0, 160.0, 8_000.0, 1_120), 200, None);
+    assert!(fading.energy_decay > 0.5, "{}", fading.energy_decay);
+    assert!(steady.energy_decay < 0.1, "{}", steady.energy_decay);
+}
+
+#[test]
+fn reset_and_long_pauses_start_a_new_turn() {
+    let mut turn = engine();
+    after_pause(&mut turn, &glide(200.0, 110.0, 6_000.0, 1_000), 2_000, Some("ok."));
+    // Speech after the hard limit starts a fresh phrase contour.
+    turn.predict(&pcm16(&glide(150.0, 150.0, 6_000.0, 40)), None);
+    assert_eq!(turn.phrase.chunks, 1);
+
+    turn.reset();
+    assert_eq!(turn.stream_position, 0);
+    let prediction = turn.predict(&pcm16(&[0.0; 640]), None);
+    assert_eq!((prediction.probability, prediction.hint_score), (0.0, 0.0));
+}
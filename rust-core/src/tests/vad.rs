@@ -0,0 +1,124 @@
+use super::{engine, pcm16, run_vad, segments, tone};
+use crate::*;
+
+/// `ms` of 16 kHz audio: a 440 Hz tone when `loud`, digital silence otherwise.
+pub(super) fn audio(parts: &[(bool, usize)]) -> Vec<f32> {
+    parts
+        .iter()
+        .flat_map(|&(loud, ms)| {
+            let len = ms * 16;
+            if loud { tone(440.0, 8_000.0, len, 16_000) } else { vec![0.0; len] }
+        })
+        .collect()
+}
+
+fn offset_of(steps: &[VadStep], kind: VadEventKind) -> Option<u64> {
+    steps.iter().find(|s| s.kind == kind).map(|s| s.sample_offset)
+}
+
+#[test]
+fn places_boundaries_on_the_sample_clock() {
+    let mut vad = engine(500.0, 300, VadOptions::default());
+    let steps = run_vad(&mut vad, &audio(&[(false, 500), (true, 1_000), (false, 1_000)]), 20);
+
+    assert_eq!(offset_of(&steps, VadEventKind::SpeechStart), Some(8_000));
+    assert_eq!(segments(&steps), vec![(500.0, 1_500.0)]);
+    // Decided on the first chunk whose end puts the silence past 300 ms.
+    assert_eq!(offset_of(&steps, VadEventKind::SpeechEnd), Some(1_800 * 16));
+    assert_eq!(vad.stream_time_ms(), 2_500.0);
+}
+
+#[test]
+fn segmentation_does_not_depend_on_chunk_size_or_wall_clock() {
+    let samples = audio(&[(false, 600), (true, 900), (false, 1_200), (true, 600), (false, 1_200)]);
+    let expected = vec![(600.0, 1_500.0), (2_700.0, 3_300.0)];
+    for chunk_ms in [10, 20, 30, 60] {
+        let mut vad = engine(500.0, 400, VadOptions::default());
+        assert_eq!(segments(&run_vad(&mut vad, &samples, chunk_ms)), expected, "{chunk_ms} ms chunks");
+    }
+
+    // Processing stalls between chunks must not shorten or lengthen silences.
+    let mut vad = engine(500.0, 400, VadOptions::default());
+    let bytes = pcm16(&samples);
+    let mut steps = Vec::new();
+    for (i, chunk) in bytes.chunks(640).enumerate() {
+        if i % 25 == 0 {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        steps.push(vad.step(chunk));
+    }
+    steps.push(vad.finish());
+    assert_eq!(segments(&steps), expected);
+}
+
+#[test]
+fn resampled_input_keeps_stream_time() {
+    let samples: Vec<f32> = [(false, 600usize), (true, 900), (false, 1_000)]
+        .iter()
+        .flat_map(|&(loud, ms)| {
+            let len = ms * 48;
+            if loud { tone(440.0, 8_000.0, len, 48_000) } else { vec![0.0; len] }
+        })
+        .collect();
+    let format = AudioFormat { encoding: "s16le".to_string(), channels: 1, sample_rate: 48_000 };
+    let mut vad = engine(500.0, 400, VadOptions { format: Some(format), ..Default::default() });
+    let bytes = pcm16(&samples);
+    let mut steps: Vec<VadStep> = bytes.chunks(960 * 2).map(|c| vad.step(c)).collect();
+    steps.push(vad.finish());
+
+    let found = segments(&steps);
+    assert_eq!(found.len(), 1);
+    assert!((found[0].0 - 600.0).abs() <= 20.0 && (found[0].1 - 1_500.0).abs() <= 20.0, "{found:?}");
+    assert!((vad.stream_time_ms() - 2_500.0).abs() < 1.0);
+}
+
+#[test]
+fn flush_closes_an_open_segment() {
+    let mut vad = engine(500.0, 300, VadOptions::default());
+    // Speech runs to the end of the stream.
+    assert_eq!(segments(&run_vad(&mut vad, &audio(&[(false, 200), (true, 400)]), 20)), vec![(200.0, 600.0)]);
+
+    // Trailing silence shorter than the timeout ends the segment where it began.
+    vad.reset();
+    assert_eq!(segments(&run_vad(&mut vad, &audio(&[(true, 400), (false, 100)]), 20)), vec![(0.0, 400.0)]);
+
+    // Nothing open: flush is silent.
+    assert_eq!(vad.finish().kind, VadEventKind::Silent);
+}
+
+#[test]
+fn reset_rewinds_the_sample_clock() {
+    let mut vad = engine(500.0, 300, VadOptions::default());
+    run_vad(&mut vad, &audio(&[(true, 500)]), 20);
+    vad.reset();
+    assert_eq!(vad.stream_time_ms(), 0.0);
+    let steps = run_vad(&mut vad, &audio(&[(false, 100), (true, 200)]), 20);
+    assert_eq!(offset_of(&steps, VadEventKind::SpeechStart), Some(1_600));
+}
+
+#[test]
+fn reports_segment_levels() {
+    let mut vad = engine(500.0, 300, VadOptions::default());
+    let steps = run_vad(&mut vad, &audio(&[(false, 200), (true, 400), (false, 600)]), 20);
+    let end = steps.iter().find(|s| s.kind == VadEventKind::SpeechEnd).unwrap();
+    let tone_rms = 8_000.0 / 2f64.sqrt();
+    assert!((end.peak_rms.unwrap() - tone_rms).abs() < 50.0);
+    assert!((end.mean_rms.unwrap() - tone_rms).abs() < 50.0);
+    assert!(steps.iter().filter(|s| s.kind == VadEventKind::Silent).all(|s| s.segment_start_ms.is_none()));
+}
+
+#[test]
+fn buffer_views_at_odd_offsets_decode_like_aligned_chunks() {
+    // `process` hands the Buffer's bytes to `step`; pooled Node Buffers are
+    // views that can start at any byte offset of their allocation.
+    let chunk = pcm16(&tone(440.0, 8_000.0, 320, 16_000));
+    let mut pooled = vec![0u8];
+    pooled.extend_from_slice(&chunk);
+
+    let mut aligned = engine(500.0, 300, VadOptions::default());
+    let mut unaligned = engine(500.0, 300, VadOptions::default());
+    let a = aligned.step(&chunk);
+    let b = unaligned.step(&pooled[1..]);
+    assert_eq!((a.kind, a.sample_count, a.rms), (b.kind, b.sample_count, b.rms));
+    assert_eq!(b.kind, VadEventKind::SpeechStart);
+}
@@ -0,0 +1,96 @@
+use super::pcm16;
+use super::vad::audio;
+use crate::*;
+
+/// RIFF/WAVE file with a plain `fmt ` chunk, an odd-sized chunk to skip and `data`.
+fn wav(tag: u16, channels: u16, sample_rate: u32, bits: u16, data: &[u8]) -> Vec<u8> {
+    let mut fmt = Vec::new();
+    fmt.extend_from_slice(&tag.to_le_bytes());
+    fmt.extend_from_slice(&channels.to_le_bytes());
+    fmt.extend_from_slice(&sample_rate.to_le_bytes());
+    let block = channels * bits / 8;
+    fmt.extend_from_slice(&(sample_rate * block as u32).to_le_bytes());
+    fmt.extend_from_slice(&block.to_le_bytes());
+    fmt.extend_from_slice(&bits.to_le_bytes());
+
+    let mut out = b"RIFF\0\0\0\0WAVE".to_vec();
+    for (id, body) in [(b"fmt ", fmt.as_slice()), (b"LIST", b"abc".as_slice()), (b"data", data)] {
+        out.extend_from_slice(id);
+        out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        out.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            out.push(0);
+        }
+    }
+    out
+}
+
+fn bounds(steps: &[VadStep]) -> Vec<(f64, f64)> {
+    steps.iter().map(|s| (s.segment_start_ms.unwrap(), s.segment_end_ms.unwrap())).collect()
+}
+
+#[test]
+fn reads_the_format_from_the_header() {
+    let data = pcm16(&[1.0, 2.0]);
+    let bytes = wav(1, 2, 44_100, 16, &data);
+    let (format, body) = parse_wav(&bytes).unwrap();
+    assert_eq!((format.encoding.as_str(), format.channels, format.sample_rate), ("s16le", 2, 44_100));
+    assert_eq!(body, data.as_slice());
+
+    for (tag, bits, encoding) in [(1, 8, "u8"), (1, 24, "s24le"), (3, 32, "f32le"), (6, 8, "alaw"), (7, 8, "mulaw")] {
+        let (format, _) = parse_wav(&wav(tag, 1, 8_000, bits, &[])).unwrap();
+        assert_eq!(format.encoding, encoding);
+    }
+}
+
+#[test]
+fn reads_extensible_headers_and_unset_sizes() {
+    let mut bytes = wav(0xFFFE, 1, 16_000, 32, &[]);
+    // Grow `fmt ` to 26 bytes with a float sub-format tag.
+    let fmt_at = 12;
+    bytes[fmt_at + 4] = 26;
+    bytes.splice(fmt_at + 8 + 16..fmt_at + 8 + 16, [10, 0, 32, 0, 4, 0, 0, 0, 3, 0]);
+    assert_eq!(parse_wav(&bytes).unwrap().0.encoding, "f32le");
+
+    // Streaming writers leave the data size at its maximum.
+    let mut streamed = wav(1, 1, 16_000, 16, &pcm16(&[5.0; 4]));
+    let len = streamed.len();
+    streamed[len - 12..len - 8].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(parse_wav(&streamed).unwrap().1.len(), 8);
+}
+
+#[test]
+fn rejects_malformed_files() {
+    assert!(parse_wav(b"not a wav file").is_err());
+    assert!(parse_wav(&wav(2, 1, 16_000, 4, &[])).is_err());
+    let data_only = [b"RIFF\0\0\0\0WAVEdata\x02\0\0\0\0\0".as_slice()].concat();
+    assert!(parse_wav(&data_only).is_err());
+    assert!(parse_wav(b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0").is_err());
+}
+
+#[test]
+fn segments_recorded_audio() {
+    let samples = audio(&[(false, 500), (true, 700), (false, 900), (true, 400), (false, 100)]);
+    let bytes = wav(1, 1, 16_000, 16, &pcm16(&samples));
+    let steps = segment_pcm(&bytes, 500.0, 300, VadOptions::default()).unwrap();
+    // The final segment is closed by the end of the file.
+    assert_eq!(bounds(&steps), vec![(500.0, 1_200.0), (2_100.0, 2_500.0)]);
+    assert!(steps.iter().all(|s| s.utterance.is_none()));
+
+    let capture = VadOptions { capture_utterances: Some(true), ..Default::default() };
+    let steps = segment_pcm(&bytes, 500.0, 300, capture).unwrap();
+    assert!(steps.iter().all(|s| s.utterance.is_some()));
+}
+
+#[test]
+fn header_format_overrides_the_format_option() {
+    let mono = audio(&[(false, 400), (true, 600), (false, 600)]);
+    let stereo: Vec<f32> = mono.iter().flat_map(|&s| [s, s]).collect();
+    let bytes = wav(1, 2, 16_000, 16, &pcm16(&stereo));
+    let wrong = VadOptions {
+        format: Some(AudioFormat { encoding: "mulaw".to_string(), channels: 1, sample_rate: 8_000 }),
+        ..Default::default()
+    };
+    let steps = segment_pcm(&bytes, 500.0, 300, wrong).unwrap();
+    assert_eq!(bounds(&steps), vec![(400.0, 1_000.0)]);
+}
@@ -1,12 +1,19 @@
-
 import { createServer, type Server } from "node:http";
+import { handleMetricsHttpRequest } from "../metrics-http.js";
 
 
 export function createGatewayHttpServer(_opts: any): Server {
     const server = createServer((req, res) => {
         // Logic to route incoming HTTP requests (static assets, API, hooks).
         // Omitted for brevity in this stage, will be copied from original.
-        res.end("Gateway HTTP Server Running");
+        void handleMetricsHttpRequest(req, res, { auth: _opts.resolvedAuth, trustedProxies: _opts.trustedProxies ?? [] })
+            .then((handled) => {
+                if (!handled) res.end("Gateway HTTP Server Running");
+            })
+            .catch(() => {
+                res.statusCode = 500;
+                res.end();
+            });
     });
     return server;
 }
@@ -0,0 +1,48 @@
+import type { IncomingMessage, ServerResponse } from "node:http";
+import { authorizeGatewayConnect, type ResolvedGatewayAuth } from "./auth.js";
+import { getBearerToken } from "./http-utils.js";
+import { sendText, sendUnauthorized } from "./http-common.js";
+import { GatewayMetrics } from "./server-metrics.js";
+
+const PROMETHEUS_CONTENT_TYPE = "text/plain; version=0.0.4; charset=utf-8";
+const OPENMETRICS_CONTENT_TYPE = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/** Serves `GET /metrics` for Prometheus scrapers, behind gateway auth. */
+export async function handleMetricsHttpRequest(
+  req: IncomingMessage,
+  res: ServerResponse,
+  opts: { auth: ResolvedGatewayAuth; trustedProxies: string[] },
+): Promise<boolean> {
+  const url = new URL(req.url ?? "/", `http://${req.headers.host || "localhost"}`);
+  if (url.pathname !== "/metrics") return false;
+
+  if (req.method !== "GET" && req.method !== "HEAD") {
+    res.statusCode = 405;
+    res.setHeader("Allow", "GET, HEAD");
+    res.end();
+    return true;
+  }
+
+  const token = getBearerToken(req);
+  const authResult = await authorizeGatewayConnect({
+    auth: opts.auth,
+    connectAuth: { token, password: token },
+    req,
+    trustedProxies: opts.trustedProxies,
+  });
+  if (!authResult.ok) {
+    sendUnauthorized(res);
+    return true;
+  }
+
+  const openMetrics = (req.headers.accept ?? "").includes("application/openmetrics-text");
+  const body = GatewayMetrics.renderPrometheus(openMetrics);
+  if (body === null) {
+    sendText(res, 503, "metrics unavailable: native MetricsEngine failed to load\n");
+    return true;
+  }
+  res.statusCode = 200;
+  res.setHeader("Content-Type", openMetrics ? OPENMETRICS_CONTENT_TYPE : PROMETHEUS_CONTENT_TYPE);
+  res.end(req.method === "HEAD" ? undefined : body);
+  return true;
+}
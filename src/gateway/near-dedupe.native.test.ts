@@ -0,0 +1,23 @@
+import { describe, it, expect } from "vitest";
+import { RatchetNearDedupe } from "../../rust-core/index.js";
+
+const MESSAGE = "Hi team, the billing deploy is scheduled for Thursday at 10am, please flag any blockers.";
+
+describe("RatchetNearDedupe (native)", () => {
+  it("matches re-deliveries within a scope only", () => {
+    const near = new RatchetNearDedupe(60_000);
+    expect(near.check(MESSAGE, 0, "whatsapp:alice").duplicate).toBe(false);
+    expect(near.check(MESSAGE, 1, "whatsapp:bob").duplicate).toBe(false);
+    expect(near.check(MESSAGE.toUpperCase().replace(",", " -"), 2, "whatsapp:alice").duplicate).toBe(true);
+  });
+
+  it("skips short replies and reports evictions", () => {
+    const near = new RatchetNearDedupe(60_000, { maxSize: 1 });
+    const ok = near.check("ok", 0);
+    expect(ok.skipped).toBe(true);
+    expect(near.check("ok", 1).duplicate).toBe(false);
+    expect(near.check("alpha bravo charlie delta", 2).evicted).toBe(false);
+    expect(near.check("echo foxtrot golf hotel", 3).evicted).toBe(true);
+    expect(near.size()).toBe(1);
+  });
+});
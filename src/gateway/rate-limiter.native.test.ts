@@ -0,0 +1,53 @@
+import { describe, it, expect } from "vitest";
+import * as ratchet from "../../rust-core/index.js";
+import { RateLimitAlgorithm, RatchetRateLimiter } from "../../rust-core/index.js";
+
+describe("@zero/ratchet exports", () => {
+  it("exports every native class and function", () => {
+    const exported = [
+      ratchet.VadEngine,
+      ratchet.BackchannelEngine,
+      ratchet.TurnTakingEngine,
+      ratchet.BargeInDetector,
+      ratchet.MetricsEngine,
+      ratchet.RatchetDedupe,
+      ratchet.RatchetBloomDedupe,
+      ratchet.RatchetNearDedupe,
+      ratchet.RatchetRateLimiter,
+      ratchet.segmentWav,
+    ];
+    for (const value of exported) {
+      expect(typeof value).toBe("function");
+    }
+  });
+});
+
+describe("RatchetRateLimiter (native)", () => {
+  it("limits a token bucket and reports the retry delay", () => {
+    const limiter = new RatchetRateLimiter({ limit: 2, windowMs: 1_000 });
+    expect(limiter.check("ip", 1, 0).allowed).toBe(true);
+    expect(limiter.check("ip", 1, 0).allowed).toBe(true);
+    const denied = limiter.check("ip", 1, 0);
+    expect(denied.allowed).toBe(false);
+    expect(denied.retryAfterMs).toBe(500);
+    expect(limiter.check("ip", 1, 500).allowed).toBe(true);
+  });
+
+  it("rejects zero and oversized costs", () => {
+    const limiter = new RatchetRateLimiter({ algorithm: RateLimitAlgorithm.SlidingWindow, limit: 3, windowMs: 1_000 });
+    expect(() => limiter.check("ip", 0)).toThrow(/positive/);
+    expect(() => limiter.check("ip", 4)).toThrow(/exceeds/);
+    expect(limiter.size()).toBe(0);
+  });
+
+  it("evicts the least recently checked key", () => {
+    const limiter = new RatchetRateLimiter({ limit: 1, windowMs: 60_000, maxKeys: 2 });
+    limiter.check("a", 1, 0);
+    limiter.check("b", 1, 0);
+    limiter.check("a", 1, 1);
+    limiter.check("c", 1, 2);
+    expect(limiter.size()).toBe(2);
+    expect(limiter.check("a", 1, 3).allowed).toBe(false);
+    expect(limiter.check("b", 1, 3).allowed).toBe(true);
+  });
+});
@@ -26,5 +26,6 @@ export const getHandshakeTimeoutMs = () => {
 };
 export const TICK_INTERVAL_MS = 30_000;
 export const HEALTH_REFRESH_INTERVAL_MS = 60_000;
+export const METRICS_PERSIST_INTERVAL_MS = 5 * 60_000;
 export const DEDUPE_TTL_MS = 5 * 60_000;
 export const DEDUPE_MAX = 1000;
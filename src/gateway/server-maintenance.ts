@@ -6,6 +6,7 @@ import {
   DEDUPE_MAX,
   DEDUPE_TTL_MS,
   HEALTH_REFRESH_INTERVAL_MS,
+  METRICS_PERSIST_INTERVAL_MS,
   TICK_INTERVAL_MS,
 } from "./server-constants.js";
 import type { DedupeEntry } from "./server-shared.js";
@@ -44,6 +45,7 @@ export function startGatewayMaintenanceTimers(params: {
   tickInterval: ReturnType<typeof setInterval>;
   healthInterval: ReturnType<typeof setInterval>;
   dedupeCleanup: ReturnType<typeof setInterval>;
+  metricsPersist: ReturnType<typeof setInterval>;
 } {
   setBroadcastHealthUpdate((snap: HealthSummary) => {
     params.broadcast("health", snap, {
@@ -55,6 +57,10 @@ export function startGatewayMaintenanceTimers(params: {
     params.nodeSendToAllSubscribed("health", snap);
   });
 
+  // Usage totals survive restarts: pick up where the last process stopped.
+  GatewayMetrics.loadFromDisk();
+  const metricsPersist = setInterval(() => GatewayMetrics.persistToDisk(), METRICS_PERSIST_INTERVAL_MS);
+
   // periodic keepalive
   const tickInterval = setInterval(() => {
     if (isPanicMode()) {
@@ -125,5 +131,5 @@ export function startGatewayMaintenanceTimers(params: {
     }
   }, 60_000);
 
-  return { tickInterval, healthInterval, dedupeCleanup };
+  return { tickInterval, healthInterval, dedupeCleanup, metricsPersist };
 }
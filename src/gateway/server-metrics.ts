@@ -1,8 +1,13 @@
+import fs from "node:fs";
+import path from "node:path";
 import { MetricsEngine as NativeMetrics } from "@zero/ratchet";
+import { resolveStateDir } from "../config/paths.js";
 import { createSubsystemLogger } from "../logging/subsystem.js";
 
 const log = createSubsystemLogger("gateway/metrics");
 
+const SNAPSHOT_PATH = path.join(resolveStateDir(), "gateway", "metrics.snapshot");
+
 let metricsEngine: NativeMetrics | null = null;
 try {
   metricsEngine = new NativeMetrics();
@@ -26,4 +31,51 @@ export class GatewayMetrics {
     if (!metricsEngine) return null;
     return metricsEngine.summarize();
   }
+
+  static renderPrometheus(openMetrics = false) {
+    return metricsEngine?.renderPrometheus(openMetrics) ?? null;
+  }
+
+  static snapshot() {
+    return metricsEngine?.snapshot() ?? null;
+  }
+
+  /** Replaces the live engine with a snapshot; a rejected snapshot keeps the current one. */
+  static restore(snapshot: Buffer): boolean {
+    if (!metricsEngine) return false;
+    try {
+      metricsEngine = NativeMetrics.restore(snapshot);
+      return true;
+    } catch (err) {
+      log.warn(`metrics snapshot rejected, keeping current metrics: ${String(err)}`);
+      return false;
+    }
+  }
+
+  /** Restores the snapshot left by the previous process, if any. */
+  static loadFromDisk(file = SNAPSHOT_PATH): boolean {
+    if (!metricsEngine || !fs.existsSync(file)) return false;
+    try {
+      return GatewayMetrics.restore(fs.readFileSync(file));
+    } catch (err) {
+      log.warn(`failed to read metrics snapshot: ${String(err)}`);
+      return false;
+    }
+  }
+
+  /** Writes the current snapshot, replacing the previous one atomically. */
+  static persistToDisk(file = SNAPSHOT_PATH): boolean {
+    const snapshot = GatewayMetrics.snapshot();
+    if (!snapshot) return false;
+    try {
+      fs.mkdirSync(path.dirname(file), { recursive: true, mode: 0o700 });
+      const tmp = `${file}.tmp`;
+      fs.writeFileSync(tmp, snapshot, { mode: 0o600 });
+      fs.renameSync(tmp, file);
+      return true;
+    } catch (err) {
+      log.error(`failed to persist metrics snapshot: ${String(err)}`);
+      return false;
+    }
+  }
 }
@@ -0,0 +1,56 @@
+import { describe, it, expect } from "vitest";
+import { BackchannelCueKind, BackchannelEngine, TurnTakingEngine } from "../../rust-core/index.js";
+
+const SAMPLE_RATE = 16_000;
+
+// Vowel-like audio whose pitch glides from `fromHz` to `toHz`.
+function glide(fromHz: number, toHz: number, ms: number, amplitude = 6_000): Buffer {
+  const samples = (SAMPLE_RATE * ms) / 1000;
+  const buffer = Buffer.alloc(samples * 2);
+  let phase = 0;
+  for (let i = 0; i < samples; i++) {
+    phase += (2 * Math.PI * (fromHz + ((toHz - fromHz) * i) / samples)) / SAMPLE_RATE;
+    let sample = 0;
+    for (let h = 1; h <= 6; h++) sample += Math.sin(phase * h) / h;
+    buffer.writeInt16LE(Math.round(sample * amplitude), i * 2);
+  }
+  return buffer;
+}
+
+function chunks(audio: Buffer, bytes = 640): Buffer[] {
+  const out: Buffer[] = [];
+  for (let i = 0; i < audio.length; i += bytes) out.push(audio.subarray(i, i + bytes));
+  return out;
+}
+
+describe("BackchannelEngine (native)", () => {
+  it("yields once after a falling phrase fed as Buffers", () => {
+    const engine = new BackchannelEngine(20, 0);
+    const audio = Buffer.concat([glide(200, 110, 2_000), Buffer.alloc(SAMPLE_RATE * 1.2)]);
+    const yields = chunks(audio).filter((chunk) => engine.processChunk(chunk));
+    expect(yields).toHaveLength(1);
+  });
+
+  it("classifies the yield point with a typed cue", () => {
+    const engine = new BackchannelEngine(20, 0);
+    const audio = Buffer.concat([glide(200, 110, 2_000), Buffer.alloc(SAMPLE_RATE * 1.2)]);
+    const cues = chunks(audio).map((chunk) => engine.classifyChunk(chunk));
+    const found = cues.filter((cue) => cue.kind !== BackchannelCueKind.None);
+    expect(found.map((cue) => cue.kind)).toEqual([BackchannelCueKind.Agreement]);
+  });
+});
+
+describe("TurnTakingEngine (native)", () => {
+  it("ends the turn after a falling phrase fed as Buffers", () => {
+    const engine = new TurnTakingEngine();
+    const audio = Buffer.concat([glide(200, 110, 1_000), Buffer.alloc(SAMPLE_RATE * 3.2)]);
+    const predictions = chunks(audio, 1_280).map((chunk) => engine.processChunk(chunk, "that's all."));
+    expect(predictions[0].isSpeaking).toBe(true);
+    expect(predictions.at(-1)?.endOfTurn).toBe(true);
+  });
+
+  it("rejects non-finite options", () => {
+    expect(() => new TurnTakingEngine({ decisionThreshold: Number.NaN })).toThrow(/decisionThreshold/);
+    expect(() => new TurnTakingEngine({ minActiveEnergy: Number.POSITIVE_INFINITY })).toThrow(/minActiveEnergy/);
+  });
+});
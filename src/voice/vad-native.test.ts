@@ -0,0 +1,119 @@
+import fs from "node:fs";
+import os from "node:os";
+import path from "node:path";
+import { describe, it, expect } from "vitest";
+import { BargeInDetector, BargeInEvent, VadEngine, VadEventKind, segmentWav } from "../../rust-core/index.js";
+
+const SAMPLE_RATE = 16_000;
+
+function tone(ms: number, amplitude = 8_000): Buffer {
+  const samples = (SAMPLE_RATE * ms) / 1000;
+  const buffer = Buffer.alloc(samples * 2);
+  for (let i = 0; i < samples; i++) {
+    buffer.writeInt16LE(Math.round(Math.sin((2 * Math.PI * 440 * i) / SAMPLE_RATE) * amplitude), i * 2);
+  }
+  return buffer;
+}
+
+function silence(ms: number): Buffer {
+  return Buffer.alloc(((SAMPLE_RATE * ms) / 1000) * 2);
+}
+
+function wav(...parts: Buffer[]): Buffer {
+  const data = Buffer.concat(parts);
+  const header = Buffer.alloc(44);
+  header.write("RIFF", 0);
+  header.writeUInt32LE(36 + data.length, 4);
+  header.write("WAVEfmt ", 8);
+  header.writeUInt32LE(16, 16);
+  header.writeUInt16LE(1, 20);
+  header.writeUInt16LE(1, 22);
+  header.writeUInt32LE(SAMPLE_RATE, 24);
+  header.writeUInt32LE(SAMPLE_RATE * 2, 28);
+  header.writeUInt16LE(2, 32);
+  header.writeUInt16LE(16, 34);
+  header.write("data", 36);
+  header.writeUInt32LE(data.length, 40);
+  return Buffer.concat([header, data]);
+}
+
+describe("VadEngine (native)", () => {
+  it("reports typed event kinds", () => {
+    const vad = new VadEngine(500, 100);
+    expect(vad.process(silence(20)).kind).toBe(VadEventKind.Silent);
+    expect(vad.process(tone(20)).kind).toBe(VadEventKind.SpeechStart);
+    expect(vad.process(tone(20)).kind).toBe(VadEventKind.Talking);
+    expect(vad.process(silence(60)).kind).toBe(VadEventKind.Silencing);
+    expect(vad.process(silence(60)).kind).toBe(VadEventKind.SpeechEnd);
+    expect(vad.processChunk(silence(20))).toBe("silent");
+  });
+
+  it("accepts Buffer views and Uint8Arrays", () => {
+    // A view at an odd offset into a larger allocation, as pooled Buffers are.
+    const pool = Buffer.concat([Buffer.alloc(1), tone(20)]);
+    const vad = new VadEngine(500, 100);
+    expect(vad.process(pool.subarray(1)).kind).toBe(VadEventKind.SpeechStart);
+    expect(vad.processChunk(new Uint8Array(tone(20)))).toBe(VadEventKind.Talking);
+  });
+
+  it("discounts playback pushed as Buffers", () => {
+    const vad = VadEngine.withOptions(500, 100, { echoMaxDelayMs: 100 });
+    const playback = tone(20);
+    for (let i = 0; i < 10; i++) {
+      vad.pushReference(playback);
+      vad.processChunk(playback);
+    }
+    expect(vad.lastRms).toBeGreaterThan(5_000);
+    expect(vad.lastResidualRms).toBeLessThan(vad.lastRms * 0.05);
+  });
+
+  it("exposes the kinds as a runtime enum", () => {
+    expect(VadEventKind.SpeechStart).toBe("speech_start");
+    expect(VadEventKind.SpeechEnd).toBe("speech_end");
+    expect(VadEventKind.Panic).toBe("panic");
+  });
+});
+
+describe("BargeInDetector (native)", () => {
+  it("reports typed events for Buffer chunks", () => {
+    const detector = new BargeInDetector();
+    expect(detector.processChunk(tone(20, 12_000))).toBe(BargeInEvent.Inactive);
+    detector.setAssistantSpeaking(true);
+    detector.pushReference(silence(20));
+    const events = Array.from({ length: 30 }, () => detector.processChunk(tone(20, 12_000)));
+    expect(events).toContain(BargeInEvent.Listening);
+    expect(events.filter((e) => e === BargeInEvent.Interrupt)).toHaveLength(1);
+  });
+});
+
+describe("segmentWav (native)", () => {
+  const recording = wav(silence(500), tone(700), silence(900), tone(400), silence(500));
+
+  it("segments off the JS thread and resolves with utterance buffers", async () => {
+    const pending = segmentWav(recording, 500, 300, { captureUtterances: true, preRollMs: 100, hangoverMs: 0 });
+    expect(pending).toBeInstanceOf(Promise);
+    const segments = await pending;
+    expect(segments.map((s) => [s.startMs, s.endMs])).toEqual([
+      [500, 1200],
+      [2100, 2500],
+    ]);
+    expect(Buffer.isBuffer(segments[0].utterance)).toBe(true);
+    expect(segments[0].utterance?.length).toBe(800 * 32);
+    expect(segments[0].utteranceStartMs).toBe(400);
+  });
+
+  it("reads WAV files from disk", async () => {
+    const file = path.join(os.tmpdir(), `ratchet-segment-${process.pid}.wav`);
+    fs.writeFileSync(file, recording);
+    try {
+      expect(await segmentWav(file, 500, 300)).toHaveLength(2);
+    } finally {
+      fs.rmSync(file, { force: true });
+    }
+  });
+
+  it("rejects unreadable input", async () => {
+    await expect(segmentWav(Buffer.from("not a wav"), 500, 300)).rejects.toThrow(/RIFF/);
+    await expect(segmentWav("/nonexistent/ratchet.wav", 500, 300)).rejects.toThrow(/Failed to read/);
+  });
+});